@@ -1,64 +1,6729 @@
-use reqwest::get;
+use serde::de::{DeserializeSeed, Deserializer, MapAccess, Visitor};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{btree_map, BTreeMap, BinaryHeap, HashMap, HashSet};
 use std::error::Error;
+use std::fmt;
 use std::fs;
-use std::path::Path;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::{Arc, OnceLock, PoisonError, RwLock};
 use std::time::Duration;
 
+use crate::errors::NamedColorsError;
+
+#[cfg(feature = "download")]
 const COLORS_JSON_URL: &str = "https://raw.githubusercontent.com/teamflp/named_colors/master/named_colors.json";
 const CACHE_FILE_PATH: &str = "cache/named_colors.json"; // Path for the local cache file
 const CACHE_EXPIRATION_DURATION: Duration = Duration::from_secs(60 * 60 * 24); // Cache expiration after 24 hours
 
-/// Checks if the cache is still valid based on the file's last modification time.
-/// If the cache was modified less than 24 hours ago, it is considered valid.
-/// 
-/// # Returns:
-/// * `true` if the cache is valid.
-/// * `false` if the cache is expired or there is an error accessing the file.
-fn is_cache_valid() -> bool {
-    if let Ok(metadata) = fs::metadata(CACHE_FILE_PATH) {
-        if let Ok(modified_time) = metadata.modified() {
-            if let Ok(duration_since_modified) = modified_time.elapsed() {
-                return duration_since_modified < CACHE_EXPIRATION_DURATION;
-            }
+// Copy of the colors shipped inside the crate, used when the remote file can't be downloaded
+#[cfg(not(feature = "compressed-assets"))]
+const EMBEDDED_COLORS_JSON: &str = include_str!("../named_colors.json");
+#[cfg(feature = "compressed-assets")]
+const EMBEDDED_COLORS_GZIP: &[u8] = include_bytes!("../named_colors.json.gz");
+
+/// A type usable for the channels of a [`ColorT`].
+pub trait ChannelValue: Copy + PartialEq {
+    /// The value of a fully opaque alpha channel.
+    const OPAQUE: Self;
+}
+
+impl ChannelValue for u8 {
+    const OPAQUE: Self = 255;
+}
+
+impl ChannelValue for f32 {
+    const OPAQUE: Self = 1.0;
+}
+
+/// A color expressed with its red, green and blue channels, plus an alpha (opacity) channel,
+/// generic over the channel type.
+///
+/// `ColorT<u8>` is the regular 8-bit [`Color`]; `ColorT<f32>` keeps float channels end to end for
+/// HDR or shader work, where `1.0` is the nominal maximum but larger values are allowed.
+///
+/// It serializes to the same `{"r": .., "g": .., "b": ..}` shape used by `named_colors.json`.
+/// The alpha channel defaults to opaque and is only written out when the color is translucent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(bound(serialize = "T: ChannelValue + Serialize", deserialize = "T: ChannelValue + Deserialize<'de>"))]
+pub struct ColorT<T> {
+    pub r: T,
+    pub g: T,
+    pub b: T,
+    #[serde(default = "opaque_alpha", skip_serializing_if = "is_opaque_alpha")]
+    pub a: T,
+}
+
+/// A color with 8-bit channels, the type used throughout the crate.
+pub type Color = ColorT<u8>;
+
+/// A color with `f32` channels, see [`ColorT`].
+pub type ColorF32 = ColorT<f32>;
+
+fn opaque_alpha<T: ChannelValue>() -> T {
+    T::OPAQUE
+}
+
+fn is_opaque_alpha<T: ChannelValue>(alpha: &T) -> bool {
+    *alpha == T::OPAQUE
+}
+
+/// A color with 16 bits per channel, for HDR or imaging workflows needing more precision.
+///
+/// [`Color`] stays the default 8-bit type; both convert into each other with `From`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Color16 {
+    pub r: u16,
+    pub g: u16,
+    pub b: u16,
+}
+
+impl From<Color> for Color16 {
+    /// Scales each channel from 8 to 16 bits (`255` becomes `65535`).
+    fn from(color: Color) -> Self {
+        Color16 {
+            r: color.r as u16 * 257,
+            g: color.g as u16 * 257,
+            b: color.b as u16 * 257,
         }
     }
-    false
 }
 
+impl From<Color16> for Color {
+    /// Scales each channel from 16 down to 8 bits, rounding to the nearest value.
+    fn from(color: Color16) -> Self {
+        let scale = |channel: u16| ((channel as u32 * 255 + 32767) / 65535) as u8;
+        Color::new(scale(color.r), scale(color.g), scale(color.b))
+    }
+}
+
+/// Returns the value of an ASCII hexadecimal digit.
+const fn hex_digit(byte: u8) -> Option<u8> {
+    match byte {
+        b'0'..=b'9' => Some(byte - b'0'),
+        b'a'..=b'f' => Some(byte - b'a' + 10),
+        b'A'..=b'F' => Some(byte - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Parses `#rrggbb`, `#rrggbbaa` or their `#rgb` / `#rgba` shorthands (the `#` being optional) in a const context.
+///
+/// Used by the [`color!`](crate::color) macro; returns `None` for invalid input.
+#[doc(hidden)]
+pub const fn parse_hex_const(hex: &str) -> Option<Color> {
+    let bytes = hex.as_bytes();
+    let start = if !bytes.is_empty() && bytes[0] == b'#' { 1 } else { 0 };
+    let len = bytes.len() - start;
+    if len != 3 && len != 4 && len != 6 && len != 8 {
+        return None;
+    }
+
+    let shorthand = len < 6;
+    let count = if len == 4 || len == 8 { 4 } else { 3 };
+    let mut channels = [255u8; 4];
+    let mut i = 0;
+    while i < count {
+        // Shorthand digits are doubled: `#f80` is `#ff8800`
+        let (high, low) = if shorthand {
+            (bytes[start + i], bytes[start + i])
+        } else {
+            (bytes[start + 2 * i], bytes[start + 2 * i + 1])
+        };
+        match (hex_digit(high), hex_digit(low)) {
+            (Some(high), Some(low)) => channels[i] = high * 16 + low,
+            _ => return None,
+        }
+        i += 1;
+    }
+    Some(Color::new_rgba(channels[0], channels[1], channels[2], channels[3]))
+}
+
+/// Splits a CSS-like function call such as `hsl(120, 100%, 50%)` into its arguments.
+///
+/// Arguments may be separated by commas or whitespace, and the function name is case-insensitive.
+fn function_args<'a>(input: &'a str, name: &str) -> Result<Vec<&'a str>, NamedColorsError> {
+    let input = input.trim();
+    let open = input.find('(').ok_or_else(|| NamedColorsError::InvalidFormat(format!("expected '{}(...)', got '{}'", name, input)))?;
+    if !input[..open].trim().eq_ignore_ascii_case(name) {
+        return Err(NamedColorsError::InvalidFormat(format!("expected '{}(...)', got '{}'", name, input)));
+    }
+    let body = input[open + 1..]
+        .strip_suffix(')')
+        .ok_or_else(|| NamedColorsError::InvalidFormat(format!("missing closing parenthesis in '{}'", input)))?;
+    Ok(body.split(|c: char| c == ',' || c.is_whitespace()).filter(|arg| !arg.is_empty()).collect())
+}
+
+/// Parses a hue argument in degrees, with an optional `deg` suffix.
+fn parse_hue_arg(arg: &str) -> Result<f32, NamedColorsError> {
+    arg.strip_suffix("deg")
+        .unwrap_or(arg)
+        .parse::<f32>()
+        .map_err(|_| NamedColorsError::InvalidFormat(format!("invalid hue '{}'", arg)))
+}
+
+/// Parses a percentage argument such as `50%` into a `0.0..=1.0` fraction.
+fn parse_percent_arg(arg: &str) -> Result<f32, NamedColorsError> {
+    let value = arg
+        .strip_suffix('%')
+        .ok_or_else(|| NamedColorsError::InvalidFormat(format!("expected a percentage, got '{}'", arg)))?
+        .parse::<f32>()
+        .map_err(|_| NamedColorsError::InvalidFormat(format!("invalid percentage '{}'", arg)))?;
+    if !(0.0..=100.0).contains(&value) {
+        return Err(NamedColorsError::InvalidFormat(format!("percentage '{}' out of range", arg)));
+    }
+    Ok(value / 100.0)
+}
+
+/// Parses an `rgb()` channel argument, either `0..=255` or a percentage.
+fn parse_rgb_arg(arg: &str) -> Result<u8, NamedColorsError> {
+    if arg.ends_with('%') {
+        return Ok((parse_percent_arg(arg)? * 255.0).round() as u8);
+    }
+    arg.parse::<u8>().map_err(|_| NamedColorsError::InvalidFormat(format!("invalid channel '{}'", arg)))
+}
+
+/// Parses an alpha argument, either a `0.0..=1.0` fraction or a percentage.
+fn parse_alpha_arg(arg: &str) -> Result<u8, NamedColorsError> {
+    let alpha = if arg.ends_with('%') {
+        parse_percent_arg(arg)?
+    } else {
+        arg.parse::<f32>()
+            .ok()
+            .filter(|alpha| (0.0..=1.0).contains(alpha))
+            .ok_or_else(|| NamedColorsError::InvalidFormat(format!("invalid alpha '{}'", arg)))?
+    };
+    Ok((alpha * 255.0).round() as u8)
+}
+
+/// Parses the `(hue, percent, percent)` arguments shared by `hsl()` and `hsv()`.
+fn parse_hue_function(input: &str, name: &str) -> Result<(f32, f32, f32), NamedColorsError> {
+    match function_args(input, name)?[..] {
+        [h, a, b] => Ok((parse_hue_arg(h)?, parse_percent_arg(a)?, parse_percent_arg(b)?)),
+        ref args => Err(NamedColorsError::InvalidFormat(format!("'{}' expects 3 arguments, got {}", name, args.len()))),
+    }
+}
+
+/// Converts an sRGB channel to linear light.
+fn srgb_to_linear(channel: u8) -> f32 {
+    let c = channel as f32 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Converts a linear light channel back to sRGB.
+fn linear_to_srgb(channel: f32) -> u8 {
+    let c = channel.clamp(0.0, 1.0);
+    let c = if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+    (c * 255.0).round() as u8
+}
+
+/// Converts normalized RGB to the red-yellow-blue model of painters (Sugita & Takahashi).
+fn rgb_to_ryb(mut r: f32, mut g: f32, mut b: f32) -> (f32, f32, f32) {
+    let white = r.min(g).min(b);
+    r -= white;
+    g -= white;
+    b -= white;
+    let max_green = r.max(g).max(b);
+
+    let mut y = r.min(g);
+    r -= y;
+    g -= y;
+    if b > 0.0 && g > 0.0 {
+        b /= 2.0;
+        g /= 2.0;
+    }
+    y += g;
+    b += g;
+
+    let max_yellow = r.max(y).max(b);
+    if max_yellow > 0.0 {
+        let n = max_green / max_yellow;
+        r *= n;
+        y *= n;
+        b *= n;
+    }
+    (r + white, y + white, b + white)
+}
+
+/// Converts red-yellow-blue values back to normalized RGB, inverting [`rgb_to_ryb`].
+fn ryb_to_rgb(mut r: f32, mut y: f32, mut b: f32) -> (f32, f32, f32) {
+    let white = r.min(y).min(b);
+    r -= white;
+    y -= white;
+    b -= white;
+    let max_yellow = r.max(y).max(b);
+
+    let mut g = y.min(b);
+    y -= g;
+    b -= g;
+    if b > 0.0 && g > 0.0 {
+        b *= 2.0;
+        g *= 2.0;
+    }
+    r += y;
+    g += y;
+
+    let max_green = r.max(g).max(b);
+    if max_green > 0.0 {
+        let n = max_yellow / max_green;
+        r *= n;
+        g *= n;
+        b *= n;
+    }
+    (r + white, g + white, b + white)
+}
+
+/// Distance metrics available for nearest-color searches, from fastest to most perceptually accurate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DistanceMetric {
+    /// Plain Euclidean distance between the RGB values.
+    Euclidean,
+    /// Euclidean RGB distance weighted with the "redmean" approximation of human perception.
+    WeightedEuclidean,
+    /// Euclidean distance in CIELAB (CIE 1976 ΔE).
+    CIE76,
+    /// The CIE 1994 ΔE formula (graphic arts weights).
+    CIE94,
+}
+
+/// Computes the distance between two colors using the given metric.
+fn metric_distance(a: &Color, b: &Color, metric: DistanceMetric) -> f32 {
+    match metric {
+        DistanceMetric::Euclidean => {
+            let dr = a.r as f32 - b.r as f32;
+            let dg = a.g as f32 - b.g as f32;
+            let db = a.b as f32 - b.b as f32;
+            (dr * dr + dg * dg + db * db).sqrt()
+        }
+        DistanceMetric::WeightedEuclidean => a.redmean_distance(b),
+        DistanceMetric::CIE76 => {
+            let (l1, a1, b1) = a.to_lab();
+            let (l2, a2, b2) = b.to_lab();
+            ((l1 - l2).powi(2) + (a1 - a2).powi(2) + (b1 - b2).powi(2)).sqrt()
+        }
+        DistanceMetric::CIE94 => {
+            let (l1, a1, b1) = a.to_lab();
+            let (l2, a2, b2) = b.to_lab();
+            let c1 = (a1 * a1 + b1 * b1).sqrt();
+            let c2 = (a2 * a2 + b2 * b2).sqrt();
+            let dl = l1 - l2;
+            let dc = c1 - c2;
+            // ΔH² is derived from Δa² + Δb² - ΔC², clamped against rounding errors
+            let dh2 = ((a1 - a2).powi(2) + (b1 - b2).powi(2) - dc * dc).max(0.0);
+            let sc = 1.0 + 0.045 * c1;
+            let sh = 1.0 + 0.015 * c1;
+            (dl * dl + (dc / sc).powi(2) + dh2 / (sh * sh)).sqrt()
+        }
+    }
+}
+
+/// String representations produced by [`Color::format`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorFormat {
+    /// `#ff0000`, like [`Color::to_hex`].
+    HexLower,
+    /// `#FF0000`.
+    HexUpper,
+    /// `rgb(255, 0, 0)`.
+    Rgb,
+    /// `rgb(100%, 0%, 0%)`, percentages rounded to one decimal.
+    RgbPercent,
+    /// `hsl(0, 100%, 50%)`, values rounded to one decimal.
+    Hsl,
+    /// The shortest CSS value: the hex code when opaque, `rgba(255, 0, 0, 0.5)` otherwise.
+    Css,
+}
+
+/// Rounds a value to one decimal, so `50.196` displays as `50.2` and `100.0` as `100`.
+fn one_decimal(value: f32) -> f32 {
+    (value * 10.0).round() / 10.0
+}
+
+/// One of the RGB channels of a [`Color`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Channel {
+    Red,
+    Green,
+    Blue,
+}
+
+impl Channel {
+    /// All the channels, in RGB order.
+    pub const ALL: [Channel; 3] = [Channel::Red, Channel::Green, Channel::Blue];
+}
+
+/// Color spaces in which [`Color::blend_in`] can interpolate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlendSpace {
+    /// Interpolate the gamma-encoded sRGB values (same as [`Color::blend`]).
+    Srgb,
+    /// Interpolate in linear light, which avoids dark muddy midpoints.
+    Linear,
+    /// Interpolate HSL components, the hue going along the shorter arc of the color wheel.
+    Hsl,
+}
+
+/// Color spaces available to [`convert_palette`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    /// Hue, saturation, lightness; see [`Color::to_hsl`].
+    Hsl,
+    /// Hue, saturation, value; see [`Color::to_hsv`].
+    Hsv,
+    /// CIELAB under D65; see [`Color::to_lab`].
+    Lab,
+    /// Cyan, magenta, yellow, key (black); see [`Color::to_cmyk`].
+    Cmyk,
+    /// OKLab; see [`Color::to_oklab`].
+    Oklab,
+}
+
+/// A color converted by [`convert_palette`], with the components of its [`ColorSpace`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConvertedColor {
+    /// The three components of [`ColorSpace::Hsl`], [`ColorSpace::Hsv`], [`ColorSpace::Lab`] or [`ColorSpace::Oklab`].
+    Three(f32, f32, f32),
+    /// The four components of [`ColorSpace::Cmyk`].
+    Four(f32, f32, f32, f32),
+}
+
+impl From<(f32, f32, f32)> for ConvertedColor {
+    fn from((a, b, c): (f32, f32, f32)) -> Self {
+        ConvertedColor::Three(a, b, c)
+    }
+}
+
+impl From<(f32, f32, f32, f32)> for ConvertedColor {
+    fn from((a, b, c, d): (f32, f32, f32, f32)) -> Self {
+        ConvertedColor::Four(a, b, c, d)
+    }
+}
+
+/// Color harmony schemes used by [`Color::harmony`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HarmonyScheme {
+    /// The base color and its opposite on the color wheel.
+    Complementary,
+    /// The base color and its two neighbours 30° away.
+    Analogous,
+    /// Three colors evenly spaced 120° apart.
+    Triadic,
+    /// Four colors evenly spaced 90° apart.
+    Tetradic,
+    /// The base color and the two colors adjacent to its complement.
+    SplitComplementary,
+}
+
+impl HarmonyScheme {
+    /// Returns the hue offsets (in degrees) of the scheme, the first one being the base color.
+    pub fn angles(&self) -> &'static [f32] {
+        match self {
+            HarmonyScheme::Complementary => &[0.0, 180.0],
+            HarmonyScheme::Analogous => &[0.0, 30.0, -30.0],
+            HarmonyScheme::Triadic => &[0.0, 120.0, 240.0],
+            HarmonyScheme::Tetradic => &[0.0, 90.0, 180.0, 270.0],
+            HarmonyScheme::SplitComplementary => &[0.0, 150.0, 210.0],
+        }
+    }
+}
+
+/// The 16 basic color keywords of CSS and HTML, for code that only needs these and wants to
+/// `match` on them exhaustively instead of looking names up in a map.
+///
 /// ```rust
-/// use named_colors::colors::load_colors;
-/// use tokio;  // Necessary to run async
+/// use named_colors::colors::{BasicColor, Color};
 ///
-/// #[tokio::main]  // Create an async function for testing
-/// async fn main() {
-///     let colors = load_colors().await.unwrap();
-///     if let Some(color) = colors.get("red") {
-///         println!("RGB for red: {:?}", color);
-///     }
-/// }
+/// let teal = BasicColor::try_from("Teal").unwrap();
+/// assert_eq!(teal, BasicColor::Teal);
+/// assert_eq!(teal.as_color(), Color::new(0, 128, 128));
 /// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BasicColor {
+    /// `#000000`
+    Black,
+    /// `#c0c0c0`
+    Silver,
+    /// `#808080`
+    Gray,
+    /// `#ffffff`
+    White,
+    /// `#800000`
+    Maroon,
+    /// `#ff0000`
+    Red,
+    /// `#800080`
+    Purple,
+    /// `#ff00ff`
+    Fuchsia,
+    /// `#008000`
+    Green,
+    /// `#00ff00`
+    Lime,
+    /// `#808000`
+    Olive,
+    /// `#ffff00`
+    Yellow,
+    /// `#000080`
+    Navy,
+    /// `#0000ff`
+    Blue,
+    /// `#008080`
+    Teal,
+    /// `#00ffff`
+    Aqua,
+}
 
-pub async fn load_colors() -> Result<HashMap<String, Value>, Box<dyn Error>> {
-    // If the cache file exists and is valid, use it.
-    if Path::new(CACHE_FILE_PATH).exists() && is_cache_valid() {
-        // Load the cache file
-        let cache_content = fs::read_to_string(CACHE_FILE_PATH)?;
-        let colors: HashMap<String, Value> = serde_json::from_str(&cache_content)
-            .map_err(|err| Box::new(err) as Box<dyn Error>)?;
-        return Ok(colors);
-    } else {
-        // If the cache is absent or expired, download the JSON file.
-        let response = get(COLORS_JSON_URL).await?.text().await?;
-        
-        // Save the JSON file to the cache for future use.
-        fs::create_dir_all("cache")?;  // Create the "cache" folder if it doesn't exist
-        fs::write(CACHE_FILE_PATH, &response)?;  // Write the downloaded content to the cache
-        
-        // Load the downloaded JSON file
-        let colors: HashMap<String, Value> = serde_json::from_str(&response)
-            .map_err(|err| Box::new(err) as Box<dyn Error>)?;
-        return Ok(colors);
+impl BasicColor {
+    /// Every basic color, in the order of the CSS specification.
+    pub const ALL: [BasicColor; 16] = [
+        BasicColor::Black,
+        BasicColor::Silver,
+        BasicColor::Gray,
+        BasicColor::White,
+        BasicColor::Maroon,
+        BasicColor::Red,
+        BasicColor::Purple,
+        BasicColor::Fuchsia,
+        BasicColor::Green,
+        BasicColor::Lime,
+        BasicColor::Olive,
+        BasicColor::Yellow,
+        BasicColor::Navy,
+        BasicColor::Blue,
+        BasicColor::Teal,
+        BasicColor::Aqua,
+    ];
+
+    /// Returns the lowercase CSS keyword of the color.
+    pub const fn name(&self) -> &'static str {
+        crate::css::CSS_BASIC_COLORS[*self as usize].0
+    }
+
+    /// Returns the RGB value of the color as defined by CSS.
+    ///
+    /// These match the builtin colors except for `Green`, `#008000` in CSS, while the builtin
+    /// `green` is `#00ff00` (`Lime` here).
+    pub const fn as_color(&self) -> Color {
+        let (_, r, g, b) = crate::css::CSS_BASIC_COLORS[*self as usize];
+        Color::new(r, g, b)
+    }
+}
+
+impl TryFrom<&str> for BasicColor {
+    type Error = NamedColorsError;
+
+    /// Parses a basic color keyword, ignoring case.
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        let folded = fold_case(name.trim());
+        BasicColor::ALL
+            .into_iter()
+            .find(|color| color.name() == folded)
+            .ok_or_else(|| NamedColorsError::ColorNotFound(name.to_string()))
+    }
+}
+
+/// Types of dichromacy simulated by [`Color::simulate_colorblindness`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorBlindness {
+    /// No red (long wavelength) cones.
+    Protanopia,
+    /// No green (medium wavelength) cones.
+    Deuteranopia,
+    /// No blue (short wavelength) cones.
+    Tritanopia,
+}
+
+impl ColorBlindness {
+    /// Returns the matrix replacing the missing LMS response with one derived from the other two.
+    fn lms_projection(&self) -> [[f32; 3]; 3] {
+        match self {
+            ColorBlindness::Protanopia => [[0.0, 2.023_44, -2.525_81], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            ColorBlindness::Deuteranopia => [[1.0, 0.0, 0.0], [0.494_207, 0.0, 1.248_27], [0.0, 0.0, 1.0]],
+            ColorBlindness::Tritanopia => [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [-0.395_913, 0.801_109, 0.0]],
+        }
+    }
+}
+
+// Linear RGB to LMS cone responses and back (Viénot, Brettel & Mollon, 1999)
+const RGB_TO_LMS: [[f32; 3]; 3] = [[17.8824, 43.5161, 4.119_35], [3.455_65, 27.1554, 3.867_14], [0.029_956_6, 0.184_309, 1.467_09]];
+const LMS_TO_RGB: [[f32; 3]; 3] = [
+    [0.080_944_45, -0.130_504_41, 0.116_721_07],
+    [-0.010_248_533, 0.054_019_33, -0.113_614_71],
+    [-0.000_365_296_94, -0.004_121_614_7, 0.693_511_4],
+];
+
+/// Multiplies a 3×3 matrix by a column vector.
+fn mul_matrix(matrix: &[[f32; 3]; 3], v: [f32; 3]) -> [f32; 3] {
+    matrix.map(|row| row[0] * v[0] + row[1] * v[1] + row[2] * v[2])
+}
+
+impl Color {
+    /// Creates a new opaque color from its red, green and blue channels.
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Color { r, g, b, a: 255 }
+    }
+
+    /// Creates a new color from its red, green, blue and alpha channels.
+    pub const fn new_rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Color { r, g, b, a }
+    }
+
+    /// Returns a copy of the color with its alpha channel replaced, leaving RGB untouched.
+    pub const fn with_alpha(self, a: u8) -> Color {
+        Color { a, ..self }
+    }
+
+    /// Returns a fully opaque copy of the color (alpha `255`).
+    pub const fn opaque(self) -> Color {
+        self.with_alpha(255)
+    }
+
+    /// Converts the color to HSL.
+    ///
+    /// # Returns:
+    /// * A `(hue, saturation, lightness)` tuple where the hue is in degrees (`0.0..360.0`)
+    ///   and the saturation and lightness are fractions (`0.0..=1.0`).
+    pub fn to_hsl(&self) -> (f32, f32, f32) {
+        let r = self.r as f32 / 255.0;
+        let g = self.g as f32 / 255.0;
+        let b = self.b as f32 / 255.0;
+
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let delta = max - min;
+        let l = (max + min) / 2.0;
+
+        // Achromatic colors have no hue nor saturation
+        if delta == 0.0 {
+            return (0.0, 0.0, l);
+        }
+
+        let s = delta / (1.0 - (2.0 * l - 1.0).abs());
+        let h = if max == r {
+            60.0 * ((g - b) / delta).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / delta + 2.0)
+        } else {
+            60.0 * ((r - g) / delta + 4.0)
+        };
+
+        (h, s.min(1.0), l)
+    }
+
+    /// Creates a color from HSL components.
+    ///
+    /// The hue is in degrees and wraps around, the saturation and lightness are clamped to `0.0..=1.0`.
+    pub fn from_hsl(h: f32, s: f32, l: f32) -> Self {
+        let h = h.rem_euclid(360.0);
+        let s = s.clamp(0.0, 1.0);
+        let l = l.clamp(0.0, 1.0);
+
+        let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+        let x = c * (1.0 - ((h / 60.0) % 2.0 - 1.0).abs());
+        let m = l - c / 2.0;
+
+        let (r, g, b) = match h {
+            h if h < 60.0 => (c, x, 0.0),
+            h if h < 120.0 => (x, c, 0.0),
+            h if h < 180.0 => (0.0, c, x),
+            h if h < 240.0 => (0.0, x, c),
+            h if h < 300.0 => (x, 0.0, c),
+            _ => (c, 0.0, x),
+        };
+
+        Color::new(
+            ((r + m) * 255.0).round() as u8,
+            ((g + m) * 255.0).round() as u8,
+            ((b + m) * 255.0).round() as u8,
+        )
+    }
+
+    /// Increases the HSL saturation by `amount`, clamped to `0.0..=1.0`.
+    ///
+    /// Achromatic colors (grays) have no hue to saturate and are returned unchanged.
+    pub fn saturate(&self, amount: f32) -> Color {
+        let (h, s, l) = self.to_hsl();
+        if s == 0.0 {
+            return *self;
+        }
+        Color::from_hsl(h, (s + amount).clamp(0.0, 1.0), l).with_alpha(self.a)
+    }
+
+    /// Decreases the HSL saturation by `amount`, clamped to `0.0..=1.0`.
+    ///
+    /// A full desaturation (`amount >= 1.0`) gives the HSL grayscale of the color.
+    pub fn desaturate(&self, amount: f32) -> Color {
+        let (h, s, l) = self.to_hsl();
+        Color::from_hsl(h, (s - amount).clamp(0.0, 1.0), l).with_alpha(self.a)
+    }
+
+    /// Increases the HSL lightness by `amount`, clamped to `0.0..=1.0`.
+    pub fn lighten(&self, amount: f32) -> Color {
+        let (h, s, l) = self.to_hsl();
+        Color::from_hsl(h, s, (l + amount).clamp(0.0, 1.0)).with_alpha(self.a)
+    }
+
+    /// Decreases the HSL lightness by `amount`, clamped to `0.0..=1.0`.
+    pub fn darken(&self, amount: f32) -> Color {
+        self.lighten(-amount)
     }
-}
\ No newline at end of file
+
+    /// Rotates the HSL hue by `degrees` (positive or negative), wrapping modulo 360.
+    ///
+    /// Achromatic colors (grays) have no hue and are returned unchanged.
+    pub fn rotate_hue(&self, degrees: f32) -> Color {
+        let (h, s, l) = self.to_hsl();
+        if s == 0.0 {
+            return *self;
+        }
+        Color::from_hsl((h + degrees).rem_euclid(360.0), s, l).with_alpha(self.a)
+    }
+
+    /// Generates a harmony palette from this color.
+    ///
+    /// # Returns:
+    /// * The colors of the scheme, starting with the base color itself.
+    pub fn harmony(&self, scheme: HarmonyScheme) -> Vec<Color> {
+        scheme
+            .angles()
+            .iter()
+            .map(|&angle| if angle == 0.0 { *self } else { self.rotate_hue(angle) })
+            .collect()
+    }
+
+    /// Converts the color to linear RGB (gamma expanded), each channel in `0.0..=1.0`.
+    pub fn to_linear(&self) -> (f32, f32, f32) {
+        (srgb_to_linear(self.r), srgb_to_linear(self.g), srgb_to_linear(self.b))
+    }
+
+    /// Creates a color from linear RGB channels in `0.0..=1.0` (out of range values are clamped).
+    pub fn from_linear(r: f32, g: f32, b: f32) -> Self {
+        Color::new(linear_to_srgb(r), linear_to_srgb(g), linear_to_srgb(b))
+    }
+
+    /// Converts the color to CIELAB, using the D65 white point.
+    ///
+    /// # Returns:
+    /// * A `(l, a, b)` tuple where `l` is in `0.0..=100.0`.
+    pub fn to_lab(&self) -> (f32, f32, f32) {
+        let (r, g, b) = self.to_linear();
+        let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.950_47;
+        let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+        let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.088_83;
+
+        let f = |t: f32| if t > 0.008_856 { t.cbrt() } else { 7.787 * t + 16.0 / 116.0 };
+        let (fx, fy, fz) = (f(x), f(y), f(z));
+        (116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz))
+    }
+
+    /// Creates a color from normalized `0.0..=1.0` channels, clamping out of range values.
+    pub fn from_f32(r: f32, g: f32, b: f32) -> Self {
+        let scale = |channel: f32| (channel.clamp(0.0, 1.0) * 255.0).round() as u8;
+        Color::new(scale(r), scale(g), scale(b))
+    }
+
+    /// Returns the channels normalized to `0.0..=1.0`.
+    pub fn to_f32(&self) -> (f32, f32, f32) {
+        (self.r as f32 / 255.0, self.g as f32 / 255.0, self.b as f32 / 255.0)
+    }
+
+    /// Blends this color with `other` in sRGB space.
+    ///
+    /// `t` is clamped to `0.0..=1.0`: `0.0` returns this color and `1.0` returns `other`.
+    pub fn blend(&self, other: &Color, t: f32) -> Color {
+        self.blend_in(other, t, BlendSpace::Srgb)
+    }
+
+    /// Blends this color with `other` in the given color space.
+    ///
+    /// `t` is clamped to `0.0..=1.0`: `0.0` returns this color and `1.0` returns `other`.
+    /// The alpha channel is always interpolated linearly.
+    pub fn blend_in(&self, other: &Color, t: f32, space: BlendSpace) -> Color {
+        let t = t.clamp(0.0, 1.0);
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+        let alpha = lerp(self.a as f32, other.a as f32).round() as u8;
+
+        let blended = match space {
+            BlendSpace::Srgb => {
+                let (r1, g1, b1) = self.to_f32();
+                let (r2, g2, b2) = other.to_f32();
+                Color::from_f32(lerp(r1, r2), lerp(g1, g2), lerp(b1, b2))
+            }
+            BlendSpace::Linear => {
+                let (r1, g1, b1) = self.to_linear();
+                let (r2, g2, b2) = other.to_linear();
+                Color::from_linear(lerp(r1, r2), lerp(g1, g2), lerp(b1, b2))
+            }
+            BlendSpace::Hsl => {
+                let (mut h1, s1, l1) = self.to_hsl();
+                let (mut h2, s2, l2) = other.to_hsl();
+                // A gray has no hue of its own, so it takes the hue of the other color
+                if s1 == 0.0 {
+                    h1 = h2;
+                }
+                if s2 == 0.0 {
+                    h2 = h1;
+                }
+                let mut delta = h2 - h1;
+                if delta > 180.0 {
+                    delta -= 360.0;
+                } else if delta < -180.0 {
+                    delta += 360.0;
+                }
+                Color::from_hsl(h1 + delta * t, lerp(s1, s2), lerp(l1, l2))
+            }
+        };
+        blended.with_alpha(alpha)
+    }
+
+    /// Checks whether the color belongs to the 216-color web-safe palette (every channel a multiple of 51).
+    pub fn is_web_safe(&self) -> bool {
+        self.to_web_safe() == *self
+    }
+
+    /// Snaps every channel to the nearest web-safe value (`0`, `51`, `102`, `153`, `204` or `255`).
+    pub fn to_web_safe(&self) -> Color {
+        let snap = |channel: u8| ((channel as u16 + 25) / 51 * 51) as u8;
+        Color::new(snap(self.r), snap(self.g), snap(self.b)).with_alpha(self.a)
+    }
+
+    /// Returns the red channel as a `0.0..=1.0` fraction.
+    pub fn red_pct(&self) -> f32 {
+        self.r as f32 / 255.0
+    }
+
+    /// Returns the green channel as a `0.0..=1.0` fraction.
+    pub fn green_pct(&self) -> f32 {
+        self.g as f32 / 255.0
+    }
+
+    /// Returns the blue channel as a `0.0..=1.0` fraction.
+    pub fn blue_pct(&self) -> f32 {
+        self.b as f32 / 255.0
+    }
+
+    /// Returns a copy with the red channel set from a `0.0..=1.0` fraction (clamped).
+    pub fn with_red_pct(self, pct: f32) -> Color {
+        Color { r: Color::from_f32(pct, 0.0, 0.0).r, ..self }
+    }
+
+    /// Returns a copy with the green channel set from a `0.0..=1.0` fraction (clamped).
+    pub fn with_green_pct(self, pct: f32) -> Color {
+        Color { g: Color::from_f32(0.0, pct, 0.0).g, ..self }
+    }
+
+    /// Returns a copy with the blue channel set from a `0.0..=1.0` fraction (clamped).
+    pub fn with_blue_pct(self, pct: f32) -> Color {
+        Color { b: Color::from_f32(0.0, 0.0, pct).b, ..self }
+    }
+
+    /// Generates `count` variants of this color's hue and saturation at evenly spaced lightness levels.
+    ///
+    /// The levels are spread over `0.0..1.0` (excluding pure black and white) and ordered from dark
+    /// to light; the level closest to the base lightness is replaced by the base color itself.
+    pub fn monochromatic(&self, count: usize) -> Vec<Color> {
+        let (h, s, l) = self.to_hsl();
+        let levels: Vec<f32> = (1..=count).map(|i| i as f32 / (count + 1) as f32).collect();
+        let base_index = levels
+            .iter()
+            .enumerate()
+            .min_by(|a, b| (a.1 - l).abs().total_cmp(&(b.1 - l).abs()))
+            .map(|(i, _)| i);
+
+        levels
+            .iter()
+            .enumerate()
+            .map(|(i, &level)| if Some(i) == base_index { *self } else { Color::from_hsl(h, s, level).with_alpha(self.a) })
+            .collect()
+    }
+
+    /// Parses a hex color code: `#rrggbb`, `#rrggbbaa` or the `#rgb` / `#rgba` shorthands, the `#` being optional.
+    ///
+    /// Codes without an alpha part are opaque.
+    pub fn from_hex(hex: &str) -> Result<Color, NamedColorsError> {
+        parse_hex_const(hex.trim()).ok_or_else(|| NamedColorsError::InvalidHex(hex.to_string()))
+    }
+
+    /// Formats the color as a lowercase `#rrggbb` hex code.
+    pub fn to_hex(&self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+
+    /// Returns the negative of the color, `(255 - r, 255 - g, 255 - b)`, keeping its alpha.
+    pub fn invert(&self) -> Color {
+        Color::new(255 - self.r, 255 - self.g, 255 - self.b).with_alpha(self.a)
+    }
+
+    /// Returns the relative luminance of the color as defined by WCAG, from `0.0` (black) to `1.0` (white).
+    pub fn luminance(&self) -> f32 {
+        let (r, g, b) = self.to_linear();
+        0.2126 * r + 0.7152 * g + 0.0722 * b
+    }
+
+    /// Returns the WCAG contrast ratio between two colors, from `1.0` (same luminance) to `21.0` (black on white).
+    pub fn contrast_ratio(&self, other: &Color) -> f32 {
+        let (l1, l2) = (self.luminance(), other.luminance());
+        (l1.max(l2) + 0.05) / (l1.min(l2) + 0.05)
+    }
+
+    /// Returns the absolute difference of relative luminance between two colors, in `0.0..=1.0`.
+    ///
+    /// Unlike [`Color::contrast_ratio`], this is a plain linear delta (no `+ 0.05` offset nor ratio),
+    /// which makes it simpler to use for ordering swatches so that neighbours stay distinct.
+    pub fn luminance_difference(&self, other: &Color) -> f32 {
+        (self.luminance() - other.luminance()).abs()
+    }
+
+    /// Returns the value of the given channel.
+    pub fn get(&self, channel: Channel) -> u8 {
+        match channel {
+            Channel::Red => self.r,
+            Channel::Green => self.g,
+            Channel::Blue => self.b,
+        }
+    }
+
+    /// Sets the value of the given channel.
+    pub fn set(&mut self, channel: Channel, value: u8) {
+        match channel {
+            Channel::Red => self.r = value,
+            Channel::Green => self.g = value,
+            Channel::Blue => self.b = value,
+        }
+    }
+
+    /// Composites this (possibly translucent) color over `background` using the "source over" operator.
+    ///
+    /// An opaque source is returned unchanged; the result is opaque when the background is.
+    pub fn over(&self, background: &Color) -> Color {
+        if self.a == 255 {
+            return *self;
+        }
+
+        let source_alpha = self.a as f32 / 255.0;
+        let background_alpha = background.a as f32 / 255.0 * (1.0 - source_alpha);
+        let alpha = source_alpha + background_alpha;
+        if alpha == 0.0 {
+            return Color::new_rgba(0, 0, 0, 0);
+        }
+
+        let composite = |source: u8, background: u8| {
+            ((source as f32 * source_alpha + background as f32 * background_alpha) / alpha).round() as u8
+        };
+        Color::new_rgba(
+            composite(self.r, background.r),
+            composite(self.g, background.g),
+            composite(self.b, background.b),
+            (alpha * 255.0).round() as u8,
+        )
+    }
+
+    /// Creates a color from `i32` channels, clamping each into `0..=255`.
+    ///
+    /// Handy after channel arithmetic, where a plain `as u8` cast would wrap around.
+    pub fn from_i32(r: i32, g: i32, b: i32) -> Self {
+        Color::new(r.clamp(0, 255) as u8, g.clamp(0, 255) as u8, b.clamp(0, 255) as u8)
+    }
+
+    /// Converts the color to HSV.
+    ///
+    /// # Returns:
+    /// * A `(hue, saturation, value)` tuple where the hue is in degrees (`0.0..360.0`)
+    ///   and the saturation and value are fractions (`0.0..=1.0`).
+    pub fn to_hsv(&self) -> (f32, f32, f32) {
+        let (r, g, b) = self.to_f32();
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let delta = max - min;
+
+        let s = if max == 0.0 { 0.0 } else { delta / max };
+        // HSL and HSV share the same hue
+        (self.to_hsl().0, s, max)
+    }
+
+    /// Creates a color from HSV components.
+    ///
+    /// The hue is in degrees and wraps around, the saturation and value are clamped to `0.0..=1.0`.
+    pub fn from_hsv(h: f32, s: f32, v: f32) -> Self {
+        let s = s.clamp(0.0, 1.0);
+        let v = v.clamp(0.0, 1.0);
+        let l = v * (1.0 - s / 2.0);
+        let hsl_s = if l == 0.0 || l == 1.0 { 0.0 } else { (v - l) / l.min(1.0 - l) };
+        Color::from_hsl(h, hsl_s, l)
+    }
+
+    /// Parses a CSS `hsl()` string such as `hsl(120, 100%, 50%)` or `hsl(120deg 100% 50%)`.
+    pub fn from_hsl_str(input: &str) -> Result<Color, NamedColorsError> {
+        let (h, s, l) = parse_hue_function(input, "hsl")?;
+        Ok(Color::from_hsl(h, s, l))
+    }
+
+    /// Parses an `hsv()` string such as `hsv(120, 100%, 100%)`.
+    pub fn from_hsv_str(input: &str) -> Result<Color, NamedColorsError> {
+        let (h, s, v) = parse_hue_function(input, "hsv")?;
+        Ok(Color::from_hsv(h, s, v))
+    }
+
+    /// Returns the channel with the largest value.
+    ///
+    /// Ties are broken in RGB order: red wins over green, which wins over blue (so any gray is `Red`).
+    pub fn dominant_channel(&self) -> Channel {
+        if self.r >= self.g && self.r >= self.b {
+            Channel::Red
+        } else if self.g >= self.b {
+            Channel::Green
+        } else {
+            Channel::Blue
+        }
+    }
+
+    /// Parses a CSS `rgb()` or `rgba()` string such as `rgb(255, 0, 0)`, `rgb(100% 0% 0%)` or `rgba(255, 0, 0, 0.5)`.
+    pub fn from_rgb_str(input: &str) -> Result<Color, NamedColorsError> {
+        let name = if input.trim_start().to_ascii_lowercase().starts_with("rgba") { "rgba" } else { "rgb" };
+        match function_args(input, name)?[..] {
+            [r, g, b] => Ok(Color::new(parse_rgb_arg(r)?, parse_rgb_arg(g)?, parse_rgb_arg(b)?)),
+            // The alpha may also follow a slash, as in `rgb(255 0 0 / 50%)`
+            [r, g, b, "/", a] | [r, g, b, a] => Ok(Color::new_rgba(parse_rgb_arg(r)?, parse_rgb_arg(g)?, parse_rgb_arg(b)?, parse_alpha_arg(a)?)),
+            ref args => Err(NamedColorsError::InvalidFormat(format!("'{}' expects 3 or 4 arguments, got {}", name, args.len()))),
+        }
+    }
+
+    /// Computes the distance to another color using the given metric.
+    ///
+    /// The scale depends on the metric: RGB metrics are in channel units, CIELAB ones in ΔE.
+    ///
+    /// # Returns:
+    /// * `0.0` for identical colors; the distance is symmetric for every metric but `CIE94`.
+    pub fn distance(&self, other: &Color, metric: DistanceMetric) -> f32 {
+        metric_distance(self, other, metric)
+    }
+
+    /// Adds `delta` to each RGB channel, clamping the result to `0..=255`; negative values darken.
+    ///
+    /// Unlike adjusting the HSL lightness, this is the direct per-channel offset of a "brightness" slider.
+    pub fn adjust_brightness(&self, delta: i16) -> Color {
+        let adjust = |channel: u8| (channel as i32 + delta as i32).clamp(0, 255) as u8;
+        Color::new(adjust(self.r), adjust(self.g), adjust(self.b)).with_alpha(self.a)
+    }
+
+    /// Packs the RGB channels into a `u32` as `0x00RRGGBB`: blue in the lowest byte, the top byte zero.
+    ///
+    /// The alpha is ignored; use [`Color::to_u32_argb`] to keep it.
+    pub const fn to_u32(&self) -> u32 {
+        (self.r as u32) << 16 | (self.g as u32) << 8 | self.b as u32
+    }
+
+    /// Unpacks a `0x00RRGGBB` value into an opaque color; the top byte is ignored.
+    pub const fn from_u32(value: u32) -> Self {
+        Color::new((value >> 16) as u8, (value >> 8) as u8, value as u8)
+    }
+
+    /// Packs the color into a `u32` as `0xAARRGGBB`: alpha in the highest byte, blue in the lowest.
+    pub const fn to_u32_argb(&self) -> u32 {
+        (self.a as u32) << 24 | self.to_u32()
+    }
+
+    /// Unpacks a `0xAARRGGBB` value, alpha in the highest byte.
+    pub const fn from_u32_argb(value: u32) -> Self {
+        Color::from_u32(value).with_alpha((value >> 24) as u8)
+    }
+
+    /// Scores how vivid the color looks, from `0.0` (gray, black or white) to `1.0` (pure hues).
+    ///
+    /// The score is `s * (1 - |2l - 1|)` with the HSL saturation `s` and lightness `l`: saturation
+    /// counts fully at mid lightness and fades out towards black and white, so muted colors, dark
+    /// shades and pastels all score low.
+    pub fn vibrancy(&self) -> f32 {
+        let (_, s, l) = self.to_hsl();
+        s * (1.0 - (2.0 * l - 1.0).abs())
+    }
+
+    /// Returns the color as linear-light RGBA floats, ready to upload as an OpenGL `vec4` uniform.
+    ///
+    /// RGB is converted from sRGB to linear (see [`Color::to_linear`]); alpha is not gamma-encoded
+    /// and is only scaled to `0.0..=1.0`, so an opaque color has an alpha of `1.0`.
+    pub fn to_gl(&self) -> [f32; 4] {
+        let (r, g, b) = self.to_linear();
+        [r, g, b, self.a as f32 / 255.0]
+    }
+
+    /// Returns the color with its HSL lightness adjusted so its WCAG luminance is close to `target`.
+    ///
+    /// The hue and saturation are kept; since luminance grows with lightness, the lightness is found
+    /// by bisection. The result is rounded to 8-bit channels, so the luminance is only approximate.
+    /// `target` is clamped to `0.0..=1.0`.
+    pub fn with_luminance(&self, target: f32) -> Color {
+        let target = target.clamp(0.0, 1.0);
+        let (h, s, _) = self.to_hsl();
+        let (mut low, mut high) = (0.0f32, 1.0f32);
+        for _ in 0..24 {
+            let mid = (low + high) / 2.0;
+            if Color::from_hsl(h, s, mid).luminance() < target {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        Color::from_hsl(h, s, high).with_alpha(self.a)
+    }
+
+    /// Formats the color in the given representation.
+    ///
+    /// Only [`ColorFormat::Css`] includes the alpha channel.
+    ///
+    /// ```rust
+    /// use named_colors::colors::{Color, ColorFormat};
+    ///
+    /// let red = Color::new(255, 0, 0);
+    /// assert_eq!(red.format(ColorFormat::HexUpper), "#FF0000");
+    /// assert_eq!(red.format(ColorFormat::Hsl), "hsl(0, 100%, 50%)");
+    /// ```
+    pub fn format(&self, fmt: ColorFormat) -> String {
+        match fmt {
+            ColorFormat::HexLower => self.to_hex(),
+            ColorFormat::HexUpper => self.to_hex().to_uppercase(),
+            ColorFormat::Rgb => format!("rgb({}, {}, {})", self.r, self.g, self.b),
+            ColorFormat::RgbPercent => {
+                let pct = |channel: u8| one_decimal(channel as f32 * 100.0 / 255.0);
+                format!("rgb({}%, {}%, {}%)", pct(self.r), pct(self.g), pct(self.b))
+            }
+            ColorFormat::Hsl => {
+                let (h, s, l) = self.to_hsl();
+                format!("hsl({}, {}%, {}%)", one_decimal(h), one_decimal(s * 100.0), one_decimal(l * 100.0))
+            }
+            ColorFormat::Css if self.a == 255 => self.to_hex(),
+            ColorFormat::Css => format!("rgba({}, {}, {}, {})", self.r, self.g, self.b, (self.a as f32 / 255.0 * 1000.0).round() / 1000.0),
+        }
+    }
+
+    /// Returns a border color for a fill: the same hue, 0.1 darker in luminance for light fills or
+    /// 0.1 lighter for dark ones.
+    ///
+    /// Whether a fill is light is decided by [`Color::is_light`].
+    pub fn border_color(&self) -> Color {
+        let luminance = self.luminance();
+        if self.is_light() {
+            self.with_luminance(luminance - 0.1)
+        } else {
+            self.with_luminance(luminance + 0.1)
+        }
+    }
+
+    /// Checks whether every channel, alpha included, differs from `other` by at most `tolerance`.
+    ///
+    /// Handy after round trips through HSL or other float spaces, where rounding makes exact
+    /// equality fail by a unit or two.
+    pub fn approx_eq(&self, other: &Color, tolerance: u8) -> bool {
+        self.r.abs_diff(other.r) <= tolerance
+            && self.g.abs_diff(other.g) <= tolerance
+            && self.b.abs_diff(other.b) <= tolerance
+            && self.a.abs_diff(other.a) <= tolerance
+    }
+
+    /// Classifies the color into one of 11 basic color terms: `"red"`, `"orange"`, `"yellow"`,
+    /// `"green"`, `"blue"`, `"purple"`, `"pink"`, `"brown"`, `"white"`, `"gray"` or `"black"`.
+    ///
+    /// Unlike a nearest-name search this is a coarse, human-friendly label. It works on HSV:
+    /// a value below `0.15` is black, a saturation below `0.08` is white (value above `0.9`) or gray,
+    /// warm hues (15°–70°) that are muted or dark are brown, and the rest is split by hue, with
+    /// pale reds counted as pink.
+    pub fn closest_basic_name(&self) -> &'static str {
+        let (h, s, v) = self.to_hsv();
+        if v < 0.15 {
+            return "black";
+        }
+        if s < 0.08 {
+            return if v > 0.9 { "white" } else { "gray" };
+        }
+        match h {
+            h if (15.0..70.0).contains(&h) => {
+                if s < 0.5 || v < 0.6 {
+                    "brown"
+                } else if h < 45.0 {
+                    "orange"
+                } else {
+                    "yellow"
+                }
+            }
+            h if (70.0..170.0).contains(&h) => "green",
+            h if (170.0..260.0).contains(&h) => "blue",
+            h if (260.0..315.0).contains(&h) => "purple",
+            h if (315.0..345.0).contains(&h) => "pink",
+            _ if s < 0.5 && v > 0.7 => "pink",
+            _ => "red",
+        }
+    }
+
+    /// Checks whether the color is light, i.e. black text contrasts more with it than white text.
+    ///
+    /// This is the case when the luminance is above about `0.18` (see [`Color::contrast_ratio`]).
+    pub fn is_light(&self) -> bool {
+        self.contrast_ratio(&Color::new(0, 0, 0)) > self.contrast_ratio(&Color::new(255, 255, 255))
+    }
+
+    /// Multiplies each RGB channel by the alpha (`channel * a / 255`, rounded), keeping the alpha.
+    ///
+    /// Compositing on premultiplied colors avoids dark fringes around translucent edges.
+    pub fn premultiply(&self) -> Color {
+        let scale = |channel: u8| ((channel as u16 * self.a as u16 + 127) / 255) as u8;
+        Color::new_rgba(scale(self.r), scale(self.g), scale(self.b), self.a)
+    }
+
+    /// Reverses [`Color::premultiply`], dividing each RGB channel by the alpha.
+    ///
+    /// A fully transparent color has no recoverable RGB and gives transparent black.
+    pub fn unpremultiply(&self) -> Color {
+        if self.a == 0 {
+            return Color::new_rgba(0, 0, 0, 0);
+        }
+        let scale = |channel: u8| ((channel as u32 * 255 + self.a as u32 / 2) / self.a as u32).min(255) as u8;
+        Color::new_rgba(scale(self.r), scale(self.g), scale(self.b), self.a)
+    }
+
+    /// Returns black or white, whichever is more readable as text on this color.
+    pub fn best_text_color(&self) -> Color {
+        if self.is_light() {
+            Color::new(0, 0, 0)
+        } else {
+            Color::new(255, 255, 255)
+        }
+    }
+
+    /// Generates a ramp of `count` colors from black through this color to white, evenly spaced.
+    ///
+    /// Samples darker than the base are shades (mixed with black) and lighter ones tints (mixed
+    /// with white). The base sits at the position of its HSL lightness, so a dark color has more
+    /// tints than shades, and the sample closest to that position is the base color itself. Unlike
+    /// [`Color::monochromatic`], the ramp includes pure black and white.
+    pub fn shades_and_tints(&self, count: usize) -> Vec<Color> {
+        let base = self.to_hsl().2;
+        let black = Color::new(0, 0, 0).with_alpha(self.a);
+        let white = Color::new(255, 255, 255).with_alpha(self.a);
+        let positions: Vec<f32> = (0..count).map(|i| if count == 1 { base } else { i as f32 / (count - 1) as f32 }).collect();
+        let base_index = positions
+            .iter()
+            .enumerate()
+            .min_by(|a, b| (a.1 - base).abs().total_cmp(&(b.1 - base).abs()))
+            .map(|(i, _)| i);
+
+        positions
+            .iter()
+            .enumerate()
+            .map(|(i, &position)| {
+                if Some(i) == base_index {
+                    *self
+                } else if position < base {
+                    black.blend(self, position / base)
+                } else {
+                    self.blend(&white, (position - base) / (1.0 - base))
+                }
+            })
+            .collect()
+    }
+
+    /// Computes the "redmean" distance to another color, a cheap approximation of perceived difference.
+    ///
+    /// With `r̄` the mean of both red channels, the squared differences are weighted by `2 + r̄/256`
+    /// for red, `4` for green and `2 + (255 - r̄)/256` for blue. This is the
+    /// [`DistanceMetric::WeightedEuclidean`] metric.
+    pub fn redmean_distance(&self, other: &Color) -> f32 {
+        let mean_r = (self.r as f32 + other.r as f32) / 2.0;
+        let dr = self.r as f32 - other.r as f32;
+        let dg = self.g as f32 - other.g as f32;
+        let db = self.b as f32 - other.b as f32;
+        ((2.0 + mean_r / 256.0) * dr * dr + 4.0 * dg * dg + (2.0 + (255.0 - mean_r) / 256.0) * db * db).sqrt()
+    }
+
+    /// Clamps every RGB channel into `min..=max`, keeping the alpha.
+    ///
+    /// Clamping to `16..=235` gives the "broadcast-safe" (TV-safe) range of video signals, and a
+    /// range like `16..=240` keeps generated palettes away from pure black and white.
+    pub fn clamp_to_range(&self, min: u8, max: u8) -> Color {
+        let clamp = |channel: u8| channel.max(min).min(max);
+        Color::new(clamp(self.r), clamp(self.g), clamp(self.b)).with_alpha(self.a)
+    }
+
+    /// Returns the name of the closest color of `color_map` if it looks the same (CIE76 ΔE of at
+    /// most `1.0`), otherwise the hex code.
+    ///
+    /// Pass [`css_extended_colors`] or [`builtin_colors`] to get CSS keywords such as `tomato`
+    /// instead of `#ff6347`. Translucent colors have no keyword and are formatted with
+    /// [`ColorFormat::Css`].
+    pub fn to_css_keyword(&self, color_map: &HashMap<String, Color>) -> String {
+        if self.a != 255 {
+            return self.format(ColorFormat::Css);
+        }
+        match nearest_color_with(color_map, self.r, self.g, self.b, DistanceMetric::CIE76) {
+            Some((name, (r, g, b))) if self.distance(&Color::new(r, g, b), DistanceMetric::CIE76) <= 1.0 => name,
+            _ => self.to_hex(),
+        }
+    }
+
+    /// Packs the HSV coordinates into an integer that sorts colors by hue, then saturation, then value.
+    ///
+    /// The key is `hue << 16 | saturation << 8 | value`, with the hue rounded to whole degrees
+    /// (`0..=359`) and the saturation and value quantized to `0..=255`. Computing it once per color
+    /// (e.g. with `sort_by_cached_key` or `sort_by_key`) avoids converting to HSV in every comparison.
+    pub fn sort_key(&self) -> u32 {
+        let (h, s, v) = self.to_hsv();
+        let hue = (h.round() as u32) % 360;
+        let quantize = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u32;
+        hue << 16 | quantize(s) << 8 | quantize(v)
+    }
+
+    /// Checks whether the color is near the gray axis: its largest and smallest RGB channels
+    /// differ by at most `tolerance`.
+    ///
+    /// Useful to set neutrals apart before sorting by hue, which is meaningless for them.
+    pub fn is_grayscale(&self, tolerance: u8) -> bool {
+        let max = self.r.max(self.g).max(self.b);
+        let min = self.r.min(self.g).min(self.b);
+        max - min <= tolerance
+    }
+
+    /// Converts the color to OKLab, a perceptually uniform space well suited to gradients.
+    ///
+    /// # Returns:
+    /// * An `(L, a, b)` tuple where `L` goes from `0.0` (black) to `1.0` (white) and `a`/`b` are
+    ///   roughly within `-0.4..0.4`.
+    pub fn to_oklab(&self) -> (f32, f32, f32) {
+        let (r, g, b) = self.to_linear();
+        let l = (0.412_221_47 * r + 0.536_332_54 * g + 0.051_445_993 * b).cbrt();
+        let m = (0.211_903_5 * r + 0.680_699_5 * g + 0.107_396_96 * b).cbrt();
+        let s = (0.088_302_46 * r + 0.281_718_85 * g + 0.629_978_7 * b).cbrt();
+        (
+            0.210_454_26 * l + 0.793_617_8 * m - 0.004_072_047 * s,
+            1.977_998_5 * l - 2.428_592_2 * m + 0.450_593_7 * s,
+            0.025_904_037 * l + 0.782_771_77 * m - 0.808_675_77 * s,
+        )
+    }
+
+    /// Creates an opaque color from OKLab coordinates (see [`Color::to_oklab`]), clamping
+    /// out-of-gamut results into sRGB.
+    pub fn from_oklab(l: f32, a: f32, b: f32) -> Self {
+        let l_ = (l + 0.396_337_78 * a + 0.215_803_76 * b).powi(3);
+        let m_ = (l - 0.105_561_346 * a - 0.063_854_17 * b).powi(3);
+        let s_ = (l - 0.089_484_18 * a - 1.291_485_5 * b).powi(3);
+        Color::from_linear(
+            4.076_741_7 * l_ - 3.307_711_6 * m_ + 0.230_969_94 * s_,
+            -1.268_438 * l_ + 2.609_757_4 * m_ - 0.341_319_4 * s_,
+            -0.004_196_086_3 * l_ - 0.703_418_6 * m_ + 1.707_614_7 * s_,
+        )
+    }
+
+    /// Converts the color to OKLCH, the polar form of OKLab.
+    ///
+    /// # Returns:
+    /// * An `(L, chroma, hue)` tuple with the hue in degrees (`0.0..360.0`).
+    pub fn to_oklch(&self) -> (f32, f32, f32) {
+        let (l, a, b) = self.to_oklab();
+        (l, (a * a + b * b).sqrt(), b.atan2(a).to_degrees().rem_euclid(360.0))
+    }
+
+    /// Creates an opaque color from OKLCH coordinates (see [`Color::to_oklch`]).
+    pub fn from_oklch(l: f32, chroma: f32, hue: f32) -> Self {
+        let (sin, cos) = hue.to_radians().sin_cos();
+        Color::from_oklab(l, chroma * cos, chroma * sin)
+    }
+
+    /// Mixes this color with `other` like paint: each color's cyan, magenta and yellow absorb light,
+    /// so blue and yellow give green, where [`Color::blend`] would give gray.
+    ///
+    /// Both colors are converted to CMY (`c = 1 - r / 255`, and so on) and combined by multiplying
+    /// the light they let through, `1 - c = (1 - c1) * (1 - c2)`, then converted back to RGB. This is
+    /// the same as multiplying the normalized RGB channels. The alpha channel is averaged.
+    ///
+    /// Like real filters, pure blue and pure yellow share no light and give black; a blue letting
+    /// some green through, such as dodger blue, gives green. See [`Color::mix_ryb`] for a painter's
+    /// model where even the pure colors give green.
+    ///
+    /// ```rust
+    /// use named_colors::colors::Color;
+    ///
+    /// let green = Color::new(30, 144, 255).mix_subtractive(&Color::new(255, 255, 0));
+    /// assert_eq!(green, Color::new(30, 144, 0));
+    /// ```
+    pub fn mix_subtractive(&self, other: &Color) -> Color {
+        let mix = |a: u8, b: u8| {
+            let (c1, c2) = (1.0 - a as f32 / 255.0, 1.0 - b as f32 / 255.0);
+            let c = 1.0 - (1.0 - c1) * (1.0 - c2);
+            1.0 - c
+        };
+        let alpha = ((self.a as f32 + other.a as f32) / 2.0).round() as u8;
+        Color::from_f32(mix(self.r, other.r), mix(self.g, other.g), mix(self.b, other.b)).with_alpha(alpha)
+    }
+
+    /// Mixes this color with `other` in equal parts in the red-yellow-blue model of painters, so
+    /// even pure blue and pure yellow give green (see [`Color::mix_subtractive`] for CMY mixing).
+    ///
+    /// The alpha channel is averaged.
+    ///
+    /// ```rust
+    /// use named_colors::colors::Color;
+    ///
+    /// let green = Color::new(0, 0, 255).mix_ryb(&Color::new(255, 255, 0));
+    /// assert_eq!(green, Color::new(0, 128, 0));
+    /// ```
+    pub fn mix_ryb(&self, other: &Color) -> Color {
+        let (r1, y1, b1) = rgb_to_ryb(self.r as f32 / 255.0, self.g as f32 / 255.0, self.b as f32 / 255.0);
+        let (r2, y2, b2) = rgb_to_ryb(other.r as f32 / 255.0, other.g as f32 / 255.0, other.b as f32 / 255.0);
+        let (r, g, b) = ryb_to_rgb((r1 + r2) / 2.0, (y1 + y2) / 2.0, (b1 + b2) / 2.0);
+        let alpha = ((self.a as f32 + other.a as f32) / 2.0).round() as u8;
+        Color::from_f32(r, g, b).with_alpha(alpha)
+    }
+
+    /// Simulates how the color looks to someone with the given type of color blindness, keeping its alpha.
+    ///
+    /// The linear RGB values are converted to LMS cone responses, the missing response is
+    /// replaced by an estimate from the other two, and the result is converted back.
+    ///
+    /// ```rust
+    /// use named_colors::colors::{Color, ColorBlindness};
+    ///
+    /// let red = Color::new(255, 0, 0).simulate_colorblindness(ColorBlindness::Deuteranopia);
+    /// let green = Color::new(0, 128, 0).simulate_colorblindness(ColorBlindness::Deuteranopia);
+    /// assert!(red.r.abs_diff(green.r) < 64);
+    /// ```
+    pub fn simulate_colorblindness(&self, kind: ColorBlindness) -> Color {
+        let (r, g, b) = self.to_linear();
+        let lms = mul_matrix(&kind.lms_projection(), mul_matrix(&RGB_TO_LMS, [r, g, b]));
+        let [r, g, b] = mul_matrix(&LMS_TO_RGB, lms);
+        Color::from_linear(r, g, b).with_alpha(self.a)
+    }
+
+    /// Posterizes the color: each channel snaps to the nearest of `levels_per_channel` evenly
+    /// spaced values from 0 to 255, keeping its alpha. 4 levels give a cube of 64 colors.
+    ///
+    /// Fewer than 2 levels are treated as 2, so channels become either 0 or 255.
+    ///
+    /// ```rust
+    /// use named_colors::colors::Color;
+    ///
+    /// assert_eq!(Color::new(100, 200, 30).quantize(3), Color::new(128, 255, 0));
+    /// ```
+    pub fn quantize(&self, levels_per_channel: u8) -> Color {
+        let step = 255.0 / (levels_per_channel.max(2) - 1) as f32;
+        let snap = |channel: u8| ((channel as f32 / step).round() * step).round() as u8;
+        Color::new(snap(self.r), snap(self.g), snap(self.b)).with_alpha(self.a)
+    }
+
+    /// Generates a Material Design style tonal palette from this color, keyed `"50"` to `"900"`
+    /// plus the accents `"A100"`, `"A200"`, `"A400"` and `"A700"`.
+    ///
+    /// `"500"` is this color. Lighter steps are mixed with white, darker ones with black:
+    ///
+    /// | Step | 50 | 100 | 200 | 300 | 400 | 500 | 600 | 700 | 800 | 900 |
+    /// |------|----|-----|-----|-----|-----|-----|-----|-----|-----|-----|
+    /// | Mix  | 90% white | 75% | 55% | 35% | 15% | - | 15% black | 30% | 45% | 60% |
+    ///
+    /// The accents are the 100, 200, 400 and 700 steps of this color at full HSL saturation. The
+    /// alpha is kept everywhere.
+    pub fn material_scale(&self) -> HashMap<String, Color> {
+        const TINTS: [(&str, f32); 5] = [("50", 0.9), ("100", 0.75), ("200", 0.55), ("300", 0.35), ("400", 0.15)];
+        const SHADES: [(&str, f32); 4] = [("600", 0.15), ("700", 0.3), ("800", 0.45), ("900", 0.6)];
+
+        let white = Color::new(255, 255, 255).with_alpha(self.a);
+        let black = Color::new(0, 0, 0).with_alpha(self.a);
+        let (h, _, l) = self.to_hsl();
+        let accent = Color::from_hsl(h, 1.0, l).with_alpha(self.a);
+
+        let mut scale = HashMap::new();
+        for (step, amount) in TINTS {
+            scale.insert(step.to_string(), self.blend(&white, amount));
+        }
+        scale.insert("500".to_string(), *self);
+        for (step, amount) in SHADES {
+            scale.insert(step.to_string(), self.blend(&black, amount));
+        }
+        for (step, amount) in [("A100", 0.75), ("A200", 0.55), ("A400", 0.15)] {
+            scale.insert(step.to_string(), accent.blend(&white, amount));
+        }
+        scale.insert("A700".to_string(), accent.blend(&black, 0.3));
+        scale
+    }
+
+    /// Returns a softer companion of the color, e.g. a background for a vivid accent, keeping its
+    /// hue and alpha.
+    ///
+    /// The HSL saturation is halved and the lightness moved a quarter of the way toward 50%.
+    pub fn muted(&self) -> Color {
+        let (h, s, l) = self.to_hsl();
+        Color::from_hsl(h, s * 0.5, l + (0.5 - l) * 0.25).with_alpha(self.a)
+    }
+
+    /// Formats the color as a Flutter/Dart `Color` constructor, e.g. `Color(0xFFFF0000)`.
+    ///
+    /// Dart packs colors as `0xAARRGGBB`: the alpha byte comes first (`FF` for opaque colors),
+    /// followed by red, green and blue, in uppercase hex.
+    pub fn to_flutter(&self) -> String {
+        format!("Color(0x{:08X})", self.to_u32_argb())
+    }
+
+    /// Estimates the correlated color temperature of the color in Kelvin: warm colors give low
+    /// values (candle light is about 1900 K) and cool ones high values (white is about 6500 K).
+    ///
+    /// This uses McCamy's approximation on the CIE xy chromaticity, which is only meaningful for
+    /// near-neutral colors such as whites and light sources; the result is clamped to
+    /// `1000.0..=25000.0`. Black has no chromaticity and is treated as white.
+    pub fn to_kelvin_approx(&self) -> f32 {
+        let (r, g, b) = self.to_linear();
+        let x = 0.4124 * r + 0.3576 * g + 0.1805 * b;
+        let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+        let z = 0.0193 * r + 0.1192 * g + 0.9505 * b;
+        let sum = x + y + z;
+        let (cx, cy) = if sum > 0.0 { (x / sum, y / sum) } else { (0.3127, 0.329) };
+
+        let n = (cx - 0.3320) / (0.1858 - cy);
+        let kelvin = 449.0 * n.powi(3) + 3525.0 * n.powi(2) + 6823.3 * n + 5520.33;
+        kelvin.clamp(1000.0, 25000.0)
+    }
+
+    /// Returns a foreground color reaching a WCAG contrast of at least `min_ratio` against this
+    /// color, like Material's "on" colors: a tint or shade of the same hue when possible.
+    ///
+    /// Keeping the HSL hue and saturation, the lightness is moved toward white on dark colors (and
+    /// toward black on light ones), bisecting for the value closest to this color that reaches the
+    /// ratio. If even white (or black) falls short, the other direction is tried; if neither works,
+    /// the result is [`Color::best_text_color`], the most readable option.
+    pub fn on_color(&self, min_ratio: f32) -> Color {
+        let (h, s, l) = self.to_hsl();
+        let first = if self.is_light() { 0.0 } else { 1.0 };
+        for extreme in [first, 1.0 - first] {
+            let candidate = |lightness: f32| Color::from_hsl(h, s, lightness);
+            if candidate(extreme).contrast_ratio(self) < min_ratio {
+                continue;
+            }
+            let (mut near, mut far) = (l, extreme);
+            for _ in 0..24 {
+                let mid = (near + far) / 2.0;
+                if candidate(mid).contrast_ratio(self) >= min_ratio {
+                    far = mid;
+                } else {
+                    near = mid;
+                }
+            }
+            return candidate(far);
+        }
+        self.best_text_color()
+    }
+
+    /// Converts the color to naive (device-independent, unprofiled) CMYK.
+    ///
+    /// # Returns:
+    /// * A `(cyan, magenta, yellow, key)` tuple of fractions (`0.0..=1.0`); black is `(0, 0, 0, 1)`.
+    pub fn to_cmyk(&self) -> (f32, f32, f32, f32) {
+        let (r, g, b) = self.to_f32();
+        let k = 1.0 - r.max(g).max(b);
+        if k >= 1.0 {
+            return (0.0, 0.0, 0.0, 1.0);
+        }
+        let ink = |channel: f32| (1.0 - channel - k) / (1.0 - k);
+        (ink(r), ink(g), ink(b), k)
+    }
+
+    /// Checks whether the color is "neon": both highly saturated and bright, as design linters
+    /// flag in professional palettes.
+    ///
+    /// Uses the HSV saturation and value, so pale tints and dark saturated colors aren't neon.
+    /// `0.9` and `0.8` are sensible thresholds: pure magenta or lime are neon, a dusty rose
+    /// such as `#dca0aa` isn't.
+    pub fn is_neon(&self, saturation_threshold: f32, lightness_min: f32) -> bool {
+        let (_, s, v) = self.to_hsv();
+        s >= saturation_threshold && v >= lightness_min
+    }
+}
+
+impl ColorF32 {
+    /// Creates a new opaque float color.
+    pub const fn new(r: f32, g: f32, b: f32) -> Self {
+        ColorT { r, g, b, a: 1.0 }
+    }
+
+    /// Creates a new float color with an alpha channel.
+    pub const fn new_rgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+        ColorT { r, g, b, a }
+    }
+
+    /// Linearly interpolates every channel, alpha included, between `self` (t = 0) and `other` (t = 1).
+    ///
+    /// `t` is clamped to `0.0..=1.0`; the channels themselves are not clamped.
+    pub fn blend(&self, other: &Self, t: f32) -> Self {
+        let t = t.clamp(0.0, 1.0);
+        let lerp = |from: f32, to: f32| from + (to - from) * t;
+        Self::new_rgba(lerp(self.r, other.r), lerp(self.g, other.g), lerp(self.b, other.b), lerp(self.a, other.a))
+    }
+}
+
+impl From<Color> for ColorF32 {
+    /// Scales each channel from `0..=255` to `0.0..=1.0`, without any gamma conversion.
+    fn from(color: Color) -> Self {
+        let scale = |channel: u8| channel as f32 / 255.0;
+        Self::new_rgba(scale(color.r), scale(color.g), scale(color.b), scale(color.a))
+    }
+}
+
+impl From<ColorF32> for Color {
+    /// Clamps each channel to `0.0..=1.0` and scales it to `0..=255`, rounding to the nearest value.
+    fn from(color: ColorF32) -> Self {
+        let scale = |channel: f32| (channel.clamp(0.0, 1.0) * 255.0).round() as u8;
+        Color::new_rgba(scale(color.r), scale(color.g), scale(color.b), scale(color.a))
+    }
+}
+
+/// Checks if the cache is still valid based on the file's last modification time.
+/// If the cache was modified less than 24 hours ago, it is considered valid.
+/// 
+/// # Returns:
+/// * `true` if the cache is valid.
+/// * `false` if the cache is expired or there is an error accessing the file.
+fn is_cache_valid() -> bool {
+    if let Ok(metadata) = fs::metadata(CACHE_FILE_PATH) {
+        if let Ok(modified_time) = metadata.modified() {
+            if let Ok(duration_since_modified) = modified_time.elapsed() {
+                return duration_since_modified < CACHE_EXPIRATION_DURATION;
+            }
+        }
+    }
+    false
+}
+
+/// Returns the colors JSON embedded in the crate.
+///
+/// With the `compressed-assets` feature the JSON is stored gzip-compressed to keep the binary small,
+/// and is only decompressed the first time it is needed.
+#[cfg(not(feature = "compressed-assets"))]
+fn embedded_colors_json() -> Result<&'static str, Box<dyn Error>> {
+    Ok(EMBEDDED_COLORS_JSON)
+}
+
+#[cfg(feature = "compressed-assets")]
+fn embedded_colors_json() -> Result<&'static str, Box<dyn Error>> {
+    static DECOMPRESSED: OnceLock<String> = OnceLock::new();
+
+    if let Some(json) = DECOMPRESSED.get() {
+        return Ok(json);
+    }
+    let json = String::from_utf8(crate::inflate::gunzip(EMBEDDED_COLORS_GZIP)?)?;
+    Ok(DECOMPRESSED.get_or_init(|| json))
+}
+
+/// Returns the raw JSON of the builtin colors embedded in the crate (the content of `named_colors.json`).
+///
+/// Tooling can re-parse it with its own serde options, or hash it for cache invalidation.
+pub fn builtin_json() -> &'static str {
+    embedded_colors_json().expect("the embedded colors are valid gzip-compressed UTF-8")
+}
+
+/// Downloads the latest colors JSON.
+///
+/// # Returns:
+/// * `Ok(None)` if the download failed (e.g. offline).
+#[cfg(feature = "download")]
+async fn download_colors_json() -> Result<Option<String>, Box<dyn Error>> {
+    match reqwest::get(COLORS_JSON_URL).await {
+        Ok(response) => Ok(Some(response.text().await?)),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Without the `download` feature nothing is downloaded, so the embedded colors are used.
+#[cfg(not(feature = "download"))]
+async fn download_colors_json() -> Result<Option<String>, Box<dyn Error>> {
+    Ok(None)
+}
+
+/// ```rust
+/// use named_colors::colors::load_colors;
+/// use tokio;  // Necessary to run async
+///
+/// #[tokio::main]  // Create an async function for testing
+/// async fn main() {
+///     let colors = load_colors().await.unwrap();
+///     if let Some(color) = colors.get("red") {
+///         println!("RGB for red: {:?}", color);
+///     }
+/// }
+/// ```
+pub async fn load_colors() -> Result<HashMap<String, Value>, Box<dyn Error>> {
+    // If the cache file exists and is valid, use it.
+    if Path::new(CACHE_FILE_PATH).exists() && is_cache_valid() {
+        // Load the cache file
+        let cache_content = fs::read_to_string(CACHE_FILE_PATH)?;
+        let colors: HashMap<String, Value> = serde_json::from_str(&cache_content)
+            .map_err(|err| Box::new(err) as Box<dyn Error>)?;
+        Ok(colors)
+    } else {
+        // If the cache is absent or expired, download the JSON file.
+        let response = match download_colors_json().await? {
+            Some(response) => response,
+            None => {
+                // Fall back to the colors embedded in the crate when the download fails (e.g. offline)
+                let colors: HashMap<String, Value> = serde_json::from_str(embedded_colors_json()?)
+                    .map_err(|err| Box::new(err) as Box<dyn Error>)?;
+                return Ok(colors);
+            }
+        };
+        
+        // Save the JSON file to the cache for future use.
+        fs::create_dir_all("cache")?;  // Create the "cache" folder if it doesn't exist
+        fs::write(CACHE_FILE_PATH, &response)?;  // Write the downloaded content to the cache
+        
+        // Load the downloaded JSON file
+        let colors: HashMap<String, Value> = serde_json::from_str(&response)
+            .map_err(|err| Box::new(err) as Box<dyn Error>)?;
+        Ok(colors)
+    }
+}
+
+/// Parses colors from a JSON string using the same layout as `named_colors.json`.
+///
+/// ```rust
+/// use named_colors::colors::{load_colors_from_file, Color};
+///
+/// let colors = load_colors_from_file(r#"{"red": {"r": 255, "g": 0, "b": 0}}"#).unwrap();
+/// assert_eq!(colors["red"], Color::new(255, 0, 0));
+/// ```
+pub fn load_colors_from_file(json_data: &str) -> Result<HashMap<String, Color>, NamedColorsError> {
+    Ok(serde_json::from_str(json_data)?)
+}
+
+/// Colors parsed by [`load_colors_from_file_strict`], along with the keys seen more than once.
+struct StrictColorMap {
+    colors: HashMap<String, Color>,
+    duplicates: Vec<String>,
+}
+
+impl<'de> Deserialize<'de> for StrictColorMap {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct StrictVisitor;
+
+        impl<'de> Visitor<'de> for StrictVisitor {
+            type Value = StrictColorMap;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a map of color names to colors")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+                let mut colors = HashMap::new();
+                let mut duplicates = Vec::new();
+                while let Some((name, color)) = access.next_entry::<String, Color>()? {
+                    if colors.contains_key(&name) && !duplicates.contains(&name) {
+                        duplicates.push(name.clone());
+                    }
+                    colors.insert(name, color);
+                }
+                Ok(StrictColorMap { colors, duplicates })
+            }
+        }
+
+        deserializer.deserialize_map(StrictVisitor)
+    }
+}
+
+/// Parses colors from a JSON string like [`load_colors_from_file`], but rejects duplicate keys.
+///
+/// `serde_json` silently keeps the last value of a duplicated key; this loader is meant for
+/// palette files treated as authoritative, where a duplicate is almost certainly a mistake.
+///
+/// # Returns:
+/// * `Err(NamedColorsError::DuplicateKeys)` listing every duplicated key, sorted alphabetically.
+pub fn load_colors_from_file_strict(json_data: &str) -> Result<HashMap<String, Color>, NamedColorsError> {
+    let mut parsed: StrictColorMap = serde_json::from_str(json_data)?;
+    if !parsed.duplicates.is_empty() {
+        parsed.duplicates.sort();
+        return Err(NamedColorsError::DuplicateKeys(parsed.duplicates));
+    }
+    Ok(parsed.colors)
+}
+
+/// Parses colors from a JSON string into a `BTreeMap`, so iterating it always yields the names in
+/// alphabetical order without sorting.
+///
+/// Names are lowercased, so `"Red"` doesn't sort before `"aqua"` and lookups by lowercase name
+/// find every color.
+///
+/// ```rust
+/// use named_colors::colors::load_colors_sorted_from_str;
+///
+/// let colors = load_colors_sorted_from_str(r#"{"Red": {"r": 255, "g": 0, "b": 0}, "aqua": {"r": 0, "g": 255, "b": 255}}"#).unwrap();
+/// assert_eq!(colors.keys().collect::<Vec<_>>(), ["aqua", "red"]);
+/// ```
+///
+/// # Returns:
+/// * `Err(NamedColorsError::DuplicateColor)` if two names only differ by case.
+pub fn load_colors_sorted_from_str(json_data: &str) -> Result<BTreeMap<String, Color>, NamedColorsError> {
+    let parsed: HashMap<String, Color> = serde_json::from_str(json_data)?;
+    let mut colors = BTreeMap::new();
+    for (name, color) in parsed {
+        let name = fold_case(&name);
+        if colors.contains_key(&name) {
+            return Err(NamedColorsError::DuplicateColor(name));
+        }
+        colors.insert(name, color);
+    }
+    Ok(colors)
+}
+
+/// Parses the builtin colors like [`builtin_colors`], but into a `BTreeMap` that iterates in
+/// alphabetical order (see [`load_colors_sorted_from_str`]).
+///
+/// ```rust
+/// use named_colors::colors::load_colors_sorted;
+///
+/// let colors = load_colors_sorted().unwrap();
+/// assert!(colors.keys().next().unwrap() < colors.keys().last().unwrap());
+/// ```
+pub fn load_colors_sorted() -> Result<BTreeMap<String, Color>, NamedColorsError> {
+    load_colors_sorted_from_str(builtin_json())
+}
+
+/// Parses colors from a JSON string, keeping the declaration order of the file.
+///
+/// Iterating a `HashMap` gives a different order on every run, which makes generated output
+/// (CSS, docs) churn; the returned `IndexMap` iterates in the order of the JSON source.
+#[cfg(feature = "indexmap")]
+pub fn load_colors_ordered(json_data: &str) -> Result<indexmap::IndexMap<String, Color>, NamedColorsError> {
+    Ok(serde_json::from_str(json_data)?)
+}
+
+/// Parses colors from a simple line-based format, as found in dotfiles.
+///
+/// Each line is either `name = #rrggbb` or `name = r, g, b`. Blank lines and lines starting with
+/// `#` or `;` are ignored, and names are normalized with [`normalize_name`].
+///
+/// ```rust
+/// use named_colors::colors::{load_colors_from_ini, Color};
+///
+/// let colors = load_colors_from_ini("; my theme\naccent = #ff6347\nbackground = 0, 0, 128").unwrap();
+/// assert_eq!(colors["background"], Color::new(0, 0, 128));
+/// ```
+///
+/// # Returns:
+/// * `Err(NamedColorsError::InvalidLine)` with the (1-based) number of the first malformed line.
+pub fn load_colors_from_ini(data: &str) -> Result<HashMap<String, Color>, NamedColorsError> {
+    let mut colors = HashMap::new();
+
+    for (index, line) in data.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        let invalid = |message: &str| NamedColorsError::InvalidLine { line: index + 1, message: message.to_string() };
+        let (name, value) = line.split_once('=').ok_or_else(|| invalid("expected 'name = value'"))?;
+        let name = normalize_name(name);
+        if name.is_empty() {
+            return Err(invalid("missing color name"));
+        }
+
+        let value = value.trim();
+        let color = if value.starts_with('#') {
+            Color::from_hex(value).map_err(|_| invalid(&format!("invalid hex color '{}'", value)))?
+        } else {
+            let channels: Vec<u8> = value
+                .split(',')
+                .map(|channel| channel.trim().parse::<u8>())
+                .collect::<Result<_, _>>()
+                .map_err(|_| invalid(&format!("invalid rgb value '{}'", value)))?;
+            match channels[..] {
+                [r, g, b] => Color::new(r, g, b),
+                _ => return Err(invalid(&format!("expected 3 channels, found {}", channels.len()))),
+            }
+        };
+        colors.insert(name, color);
+    }
+
+    Ok(colors)
+}
+
+/// Reads colors from environment-variable style `KEY=value` pairs, whose values are hex codes or
+/// `rgb()` strings, e.g. theme colors configured with `THEME_ACCENT=#ff6347`.
+///
+/// Only the keys starting with `prefix` are used, so the pairs collected from `std::env::vars()`
+/// can be passed as is. The prefix is stripped and the rest normalized (see [`normalize_name`]).
+///
+/// ```rust
+/// use named_colors::colors::{load_colors_from_env_pairs, Color};
+///
+/// let pairs = vec![
+///     ("THEME_RED".to_string(), "#ff0000".to_string()),
+///     ("HOME".to_string(), "/home/me".to_string()),
+/// ];
+/// let theme = load_colors_from_env_pairs(&pairs, "THEME_").unwrap();
+/// assert_eq!(theme["red"], Color::new(255, 0, 0));
+/// assert_eq!(theme.len(), 1);
+/// ```
+///
+/// # Returns:
+/// * `Err(NamedColorsError::InvalidName)` if a key is just the prefix.
+/// * `Err(NamedColorsError::InvalidFormat)` naming the key whose value isn't a valid color.
+pub fn load_colors_from_env_pairs(pairs: &[(String, String)], prefix: &str) -> Result<HashMap<String, Color>, NamedColorsError> {
+    let mut colors = HashMap::new();
+    for (key, value) in pairs {
+        let Some(name) = key.strip_prefix(prefix) else {
+            continue;
+        };
+        let name = normalize_name(name);
+        if name.is_empty() {
+            return Err(NamedColorsError::InvalidName(key.clone()));
+        }
+
+        let value = value.trim();
+        let color = if value.to_ascii_lowercase().starts_with("rgb") { Color::from_rgb_str(value) } else { Color::from_hex(value) };
+        let color = color.map_err(|_| NamedColorsError::InvalidFormat(format!("{}: invalid color '{}'", key, value)))?;
+        colors.insert(name, color);
+    }
+    Ok(colors)
+}
+
+/// Parses the builtin colors embedded in the crate (see [`builtin_json`]).
+pub fn builtin_colors() -> Result<HashMap<String, Color>, NamedColorsError> {
+    load_colors_from_file(builtin_json())
+}
+
+/// Returns the builtin colors parsed once and shared behind an `Arc`.
+///
+/// The first call parses the embedded JSON; every later call, from any thread, clones the same
+/// `Arc`, so servers can hand the palette to each worker without reloading or locking it.
+///
+/// ```rust
+/// use named_colors::colors::{shared_builtin, Color};
+///
+/// let colors = shared_builtin();
+/// assert_eq!(colors["red"], Color::new(255, 0, 0));
+/// ```
+pub fn shared_builtin() -> Arc<HashMap<String, Color>> {
+    static SHARED: OnceLock<Arc<HashMap<String, Color>>> = OnceLock::new();
+
+    SHARED
+        .get_or_init(|| Arc::new(builtin_colors().expect("the embedded colors JSON is valid")))
+        .clone()
+}
+
+/// Parses a GIMP palette (`.gpl`) file.
+///
+/// The header (`GIMP Palette`, `Name:` and `Columns:` lines), comments and blank lines are
+/// skipped. Each color line holds three decimal channels followed by the name, which may contain
+/// spaces and is normalized with [`normalize_name`].
+///
+/// # Returns:
+/// * `Err(NamedColorsError::InvalidLine)` with the (1-based) number of the first malformed line.
+pub fn load_colors_from_gpl(data: &str) -> Result<HashMap<String, Color>, NamedColorsError> {
+    let mut colors = HashMap::new();
+
+    for (index, line) in data.lines().enumerate() {
+        let invalid = |message: &str| NamedColorsError::InvalidLine { line: index + 1, message: message.to_string() };
+        let line = line.trim();
+        if index == 0 {
+            if line != "GIMP Palette" {
+                return Err(invalid("expected the 'GIMP Palette' header"));
+            }
+            continue;
+        }
+        if line.is_empty() || line.starts_with('#') || line.starts_with("Name:") || line.starts_with("Columns:") {
+            continue;
+        }
+
+        let mut parts = line.split_whitespace();
+        let mut channels = [0u8; 3];
+        for channel in channels.iter_mut() {
+            let part = parts.next().ok_or_else(|| invalid("expected 'r g b name'"))?;
+            *channel = part.parse().map_err(|_| invalid(&format!("invalid channel '{}'", part)))?;
+        }
+        // The name is everything after the three numbers, and may itself contain spaces
+        let name = normalize_name(&parts.collect::<Vec<&str>>().join(" "));
+        if name.is_empty() {
+            return Err(invalid("missing color name"));
+        }
+        colors.insert(name, Color::new(channels[0], channels[1], channels[2]));
+    }
+
+    Ok(colors)
+}
+
+/// Loads colors from any reader (file, network stream, `stdin`, ...) without reading it into a `String` first.
+///
+/// # Returns:
+/// * `Err(NamedColorsError::Io)` if reading fails, `Err(NamedColorsError::ParseError)` for invalid JSON.
+pub fn load_colors_from_reader<R: Read>(reader: R) -> Result<HashMap<String, Color>, NamedColorsError> {
+    serde_json::from_reader(BufReader::new(reader)).map_err(|err| {
+        if err.is_io() {
+            NamedColorsError::Io(err.into())
+        } else {
+            NamedColorsError::ParseError(err)
+        }
+    })
+}
+
+/// Parses colors from a JSON string like [`load_colors_from_file`], naming the entry that failed.
+///
+/// `serde_json` only reports a line and column, which is hard to track down in a large hand-edited
+/// palette; here each entry is parsed separately so the error says which color is broken.
+///
+/// # Returns:
+/// * `Err(NamedColorsError::InvalidColor)` with the name of the first (alphabetically) invalid entry.
+/// * `Err(NamedColorsError::ParseError)` if the input is not a JSON object at all.
+pub fn load_colors_with_context(json_data: &str) -> Result<HashMap<String, Color>, NamedColorsError> {
+    let entries: BTreeMap<String, Value> = serde_json::from_str(json_data)?;
+    entries
+        .into_iter()
+        .map(|(name, value)| match Color::deserialize(value) {
+            Ok(color) => Ok((name, color)),
+            Err(source) => Err(NamedColorsError::InvalidColor { name, source }),
+        })
+        .collect()
+}
+
+/// A line of a JSON Lines palette: `{"name": "red", "r": 255, "g": 0, "b": 0}`.
+#[derive(Deserialize)]
+struct JsonlColor {
+    name: String,
+    #[serde(flatten)]
+    color: Color,
+}
+
+/// Loads colors from JSON Lines: one `{"name": "red", "r": 255, "g": 0, "b": 0}` object per line,
+/// the append-friendly format of data pipelines. Blank lines are skipped, and a later line
+/// overrides an earlier color with the same name.
+///
+/// The input is read line by line, so huge palettes are never held in memory as a whole.
+///
+/// # Returns:
+/// * `Err(NamedColorsError::InvalidLine)` with the (1-based) number of the first malformed line.
+/// * `Err(NamedColorsError::Io)` if reading fails.
+pub fn load_colors_from_jsonl<R: Read>(reader: R) -> Result<HashMap<String, Color>, NamedColorsError> {
+    let mut colors = HashMap::new();
+    for (index, line) in BufReader::new(reader).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: JsonlColor =
+            serde_json::from_str(&line).map_err(|err| NamedColorsError::InvalidLine { line: index + 1, message: err.to_string() })?;
+        colors.insert(entry.name, entry.color);
+    }
+    Ok(colors)
+}
+
+/// A color with free-form tags such as `"pastel"` or `"brand"`, to search a palette by intent.
+///
+/// In JSON the tags sit next to the channels, `{"r": 255, "g": 209, "b": 220, "tags": ["pastel"]}`,
+/// and may be omitted, so untagged palettes load as is.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaggedColor {
+    #[serde(flatten)]
+    pub color: Color,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+/// Parses colors with their optional tags from a JSON string (see [`TaggedColor`]).
+pub fn load_tagged_colors(json_data: &str) -> Result<HashMap<String, TaggedColor>, NamedColorsError> {
+    Ok(serde_json::from_str(json_data)?)
+}
+
+/// Returns the names of the colors carrying `tag` (compared exactly), sorted alphabetically.
+pub fn colors_with_tag(color_map: &HashMap<String, TaggedColor>, tag: &str) -> Vec<String> {
+    let mut names: Vec<String> = color_map
+        .iter()
+        .filter(|(_, entry)| entry.tags.iter().any(|t| t == tag))
+        .map(|(name, _)| name.clone())
+        .collect();
+    names.sort();
+    names
+}
+
+/// Builds a parse error for malformed JSONC.
+#[cfg(feature = "jsonc")]
+fn syntax_error(message: &str) -> NamedColorsError {
+    NamedColorsError::ParseError(serde::de::Error::custom(message))
+}
+
+/// Turns JSONC into plain JSON: comments become spaces (newlines are kept, so error positions
+/// still match the input) and trailing commas before `}` or `]` are dropped.
+#[cfg(feature = "jsonc")]
+fn strip_jsonc(data: &str) -> Result<String, NamedColorsError> {
+    let mut output = String::with_capacity(data.len());
+    let mut chars = data.chars().peekable();
+    let mut in_string = false;
+
+    while let Some(c) = chars.next() {
+        if in_string {
+            output.push(c);
+            match c {
+                '\\' => output.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                output.push(c);
+            }
+            ('/', Some('/')) => {
+                while chars.next_if(|&next| next != '\n').is_some() {}
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut previous = ' ';
+                loop {
+                    let next = chars.next().ok_or_else(|| syntax_error("unterminated block comment"))?;
+                    if previous == '*' && next == '/' {
+                        break;
+                    }
+                    if next == '\n' {
+                        output.push('\n');
+                    }
+                    previous = next;
+                }
+            }
+            ('}' | ']', _) => {
+                let trimmed = output.trim_end().len();
+                if output[..trimmed].ends_with(',') {
+                    output.replace_range(trimmed - 1..trimmed, " ");
+                }
+                output.push(c);
+            }
+            _ => output.push(c),
+        }
+    }
+    Ok(output)
+}
+
+/// Parses colors from JSON with comments (`//` and `/* */`) and trailing commas, as found in
+/// hand-edited palette files. The result is the same as [`load_colors_from_file`] on the plain JSON.
+///
+/// ```rust
+/// use named_colors::colors::{load_colors_from_jsonc, Color};
+///
+/// let colors = load_colors_from_jsonc(r#"{
+///     // Brand colors
+///     "red": {"r": 255, "g": 0, "b": 0},
+/// }"#).unwrap();
+/// assert_eq!(colors["red"], Color::new(255, 0, 0));
+/// ```
+#[cfg(feature = "jsonc")]
+pub fn load_colors_from_jsonc(data: &str) -> Result<HashMap<String, Color>, NamedColorsError> {
+    load_colors_from_file(&strip_jsonc(data)?)
+}
+
+/// An entry of a palette that may reference another color, see [`load_colors_with_refs`].
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ColorOrRef {
+    Ref {
+        #[serde(rename = "ref")]
+        target: String,
+    },
+    Color(Color),
+}
+
+/// Parses colors from a JSON string where entries may reference other colors by name, as in
+/// `{"brand_primary": {"ref": "red"}, "red": {"r": 255, "g": 0, "b": 0}}`.
+///
+/// References are resolved after the whole file is read, so they can point anywhere in it and to
+/// other references; the resolved entry gets the RGB of its target.
+///
+/// # Returns:
+/// * `Err(NamedColorsError::UnresolvedRef)` if a reference points to a missing name.
+/// * `Err(NamedColorsError::CyclicRef)` if references loop back on themselves.
+pub fn load_colors_with_refs(json_data: &str) -> Result<HashMap<String, Color>, NamedColorsError> {
+    let entries: BTreeMap<String, ColorOrRef> = serde_json::from_str(json_data)?;
+
+    let mut colors = HashMap::new();
+    for name in entries.keys() {
+        // Follow the chain of references until a color, a missing name or a loop
+        let mut chain = vec![name];
+        let color = loop {
+            let current = chain[chain.len() - 1];
+            match &entries[current] {
+                ColorOrRef::Color(color) => break *color,
+                ColorOrRef::Ref { target } => {
+                    let (target, _) = entries.get_key_value(target).ok_or_else(|| NamedColorsError::UnresolvedRef {
+                        name: current.clone(),
+                        target: target.clone(),
+                    })?;
+                    if let Some(start) = chain.iter().position(|seen| *seen == target) {
+                        let mut cycle: Vec<String> = chain[start..].iter().map(|seen| seen.to_string()).collect();
+                        cycle.push(target.clone());
+                        return Err(NamedColorsError::CyclicRef(cycle));
+                    }
+                    chain.push(target);
+                }
+            }
+        };
+        colors.insert(name.clone(), color);
+    }
+    Ok(colors)
+}
+
+/// Loads colors from a JSON file on disk.
+pub fn load_colors_from_path<P: AsRef<Path>>(path: P) -> Result<HashMap<String, Color>, NamedColorsError> {
+    let json_data = fs::read_to_string(path)?;
+    load_colors_from_file(&json_data)
+}
+
+/// Loads every `.json` palette of a directory, prefixing each color with the file stem.
+///
+/// A `red` entry of `material.json` becomes `material_red` (names are normalized, see
+/// [`normalize_name`]). Other files and subdirectories are skipped, and files are read in name order.
+///
+/// # Returns:
+/// * `Err(NamedColorsError::InvalidFile)` with the path of the first palette that failed to parse.
+/// * `Err(NamedColorsError::Io)` if the directory or a file can't be read.
+pub fn load_palettes_from_dir<P: AsRef<Path>>(dir: P) -> Result<HashMap<String, Color>, NamedColorsError> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_json = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+        if is_json && path.is_file() {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    let mut colors = HashMap::new();
+    for path in paths {
+        let json_data = fs::read_to_string(&path)?;
+        let palette: HashMap<String, Color> = match serde_json::from_str(&json_data) {
+            Ok(palette) => palette,
+            Err(source) => return Err(NamedColorsError::InvalidFile { path, source }),
+        };
+        let stem = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+        for (name, color) in palette {
+            colors.insert(normalize_name(&format!("{}_{}", stem, name)), color);
+        }
+    }
+    Ok(colors)
+}
+
+/// Loads colors from a JSON file on disk without blocking the async runtime.
+///
+/// Only the read is asynchronous, the JSON is then parsed like [`load_colors_from_path`].
+#[cfg(feature = "tokio")]
+pub async fn load_colors_from_path_async<P: AsRef<Path>>(path: P) -> Result<HashMap<String, Color>, NamedColorsError> {
+    let json_data = tokio::fs::read_to_string(path).await?;
+    load_colors_from_file(&json_data)
+}
+
+/// Returns every color name whose RGB exactly matches, sorted alphabetically.
+///
+/// Unlike a single-name lookup, this surfaces aliases such as `gray` and `grey`.
+pub fn get_all_names_by_rgb(color_map: &HashMap<String, Color>, r: u8, g: u8, b: u8) -> Vec<String> {
+    let target = Color::new(r, g, b);
+    let mut names: Vec<String> = color_map
+        .iter()
+        .filter(|(_, color)| **color == target)
+        .map(|(name, _)| name.clone())
+        .collect();
+    names.sort();
+    names
+}
+
+/// Finds the name of a color matching a hex code up to `tolerance` per channel, e.g. to recover
+/// the intended name of a slightly-off code picked from a compressed image. The alpha is ignored.
+///
+/// # Returns:
+/// * The closest candidate (Euclidean RGB distance, ties broken by name), or `None` if no color
+///   is within the tolerance or `hex` isn't a valid hex code.
+pub fn get_name_by_hex_approx(color_map: &HashMap<String, Color>, hex: &str, tolerance: u8) -> Option<String> {
+    let target = Color::from_hex(hex).ok()?.with_alpha(255);
+    color_map
+        .iter()
+        .filter(|(_, color)| color.r.abs_diff(target.r) <= tolerance && color.g.abs_diff(target.g) <= tolerance && color.b.abs_diff(target.b) <= tolerance)
+        .map(|(name, color)| (metric_distance(&target, color, DistanceMetric::Euclidean), name))
+        .min_by(|a, b| a.0.total_cmp(&b.0).then_with(|| a.1.cmp(b.1)))
+        .map(|(_, name)| name.clone())
+}
+
+/// Lowercases a color name for case-insensitive lookups.
+///
+/// Only ASCII letters are folded by default. With the `unicode` feature the name is also put in
+/// Unicode NFC form and every letter is lowercased, so `"GRÜN"` and a decomposed `"grün"` match.
+#[cfg(not(feature = "unicode"))]
+pub(crate) fn fold_case(name: &str) -> String {
+    name.to_ascii_lowercase()
+}
+
+#[cfg(feature = "unicode")]
+pub(crate) fn fold_case(name: &str) -> String {
+    use unicode_normalization::UnicodeNormalization;
+
+    name.nfc().collect::<String>().to_lowercase()
+}
+
+/// Normalizes a color name into its canonical form.
+///
+/// The canonical form is trimmed, lowercased, and every run of spaces, hyphens or underscores
+/// is collapsed into a single underscore, so `"Sky Blue"`, `"sky-blue"` and `"sky_blue"` all
+/// become `"sky_blue"`. Only ASCII letters are lowercased unless the `unicode` feature is enabled.
+pub fn normalize_name(name: &str) -> String {
+    name.split(|c: char| c.is_whitespace() || c == '-' || c == '_')
+        .filter(|part| !part.is_empty())
+        .map(fold_case)
+        .collect::<Vec<String>>()
+        .join("_")
+}
+
+/// Adds a new color to the map under its normalized name (see [`normalize_name`]).
+///
+/// # Returns:
+/// * `Ok(())` if the color was added.
+/// * `Err(NamedColorsError::DuplicateColor)` if a color with the same normalized name exists.
+/// * `Err(NamedColorsError::InvalidName)` if the name is empty once normalized.
+pub fn add_color(color_map: &mut HashMap<String, Color>, name: &str, r: u8, g: u8, b: u8) -> Result<(), NamedColorsError> {
+    let key = normalize_name(name);
+    if key.is_empty() {
+        return Err(NamedColorsError::InvalidName(name.to_string()));
+    }
+    if color_map.contains_key(&key) {
+        return Err(NamedColorsError::DuplicateColor(key));
+    }
+    color_map.insert(key, Color::new(r, g, b));
+    Ok(())
+}
+
+/// Forwards the entries of a JSON palette to a channel as serde_json parses them; see [`load_colors_streaming`].
+struct ColorSender<'a>(&'a SyncSender<Result<(String, Color), NamedColorsError>>);
+
+impl<'de> DeserializeSeed<'de> for ColorSender<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ColorSender<'_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map of color names to colors")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+        while let Some(entry) = access.next_entry::<String, Color>()? {
+            if self.0.send(Ok(entry)).is_err() {
+                // The iterator was dropped, so nobody wants the remaining entries
+                return Err(serde::de::Error::custom("the color stream was dropped"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses a JSON palette entry by entry, without buffering the whole file into a map.
+///
+/// The palette is parsed by serde_json on a background thread, which hands the entries over as
+/// they are read and stays at most a few entries ahead of the iterator. The iterator stops after
+/// the first error.
+///
+/// ```rust
+/// use named_colors::colors::load_colors_streaming;
+/// use std::io::Cursor;
+///
+/// let json = r#"{"red": {"r": 255, "g": 0, "b": 0}}"#;
+/// for entry in load_colors_streaming(Cursor::new(json)) {
+///     let (name, color) = entry.unwrap();
+///     println!("{}: {:?}", name, color);
+/// }
+/// ```
+pub fn load_colors_streaming<R: Read + Send + 'static>(reader: R) -> impl Iterator<Item = Result<(String, Color), NamedColorsError>> {
+    let (sender, receiver) = mpsc::sync_channel(64);
+    std::thread::spawn(move || {
+        let mut deserializer = serde_json::Deserializer::from_reader(BufReader::new(reader));
+        let result = ColorSender(&sender).deserialize(&mut deserializer).and_then(|()| deserializer.end());
+        if let Err(err) = result {
+            // Fails only if the iterator was dropped, in which case the error doesn't matter
+            let _ = sender.send(Err(err.into()));
+        }
+    });
+    receiver.into_iter()
+}
+
+/// Looks up a color by name, returning a reference to the `Color` itself rather than an RGB tuple.
+///
+/// The exact key is tried first, then the normalized name (see [`normalize_name`]), so lookups
+/// are case-insensitive, and finally the aliases registered with [`add_alias`].
+pub fn get_color<'a, T>(color_map: &'a HashMap<String, ColorT<T>>, name: &str) -> Option<&'a ColorT<T>> {
+    color_map
+        .get(name)
+        .or_else(|| color_map.get(&normalize_name(name)))
+        .or_else(|| alias_target(name).and_then(|target| color_map.get(&target)))
+}
+
+/// Looks up the RGB values of a color like [`get_color`], falling back to `default` when the
+/// name is unknown.
+///
+/// ```rust
+/// use named_colors::colors::{builtin_colors, get_color_or};
+///
+/// let colors = builtin_colors().unwrap();
+/// assert_eq!(get_color_or(&colors, "brand", (0, 0, 0)), (0, 0, 0));
+/// ```
+pub fn get_color_or(color_map: &HashMap<String, Color>, name: &str, default: (u8, u8, u8)) -> (u8, u8, u8) {
+    get_color(color_map, name).map_or(default, |color| (color.r, color.g, color.b))
+}
+
+/// Averages the given colors in linear space.
+///
+/// Averaging in linear light rather than on the gamma-encoded values avoids midpoints that look
+/// too dark.
+///
+/// # Returns:
+/// * `Err(NamedColorsError::ColorNotFound)` with the first name missing from the map.
+/// * `Err(NamedColorsError::NoColors)` if `names` is empty.
+pub fn average_color(color_map: &HashMap<String, Color>, names: &[&str]) -> Result<Color, NamedColorsError> {
+    if names.is_empty() {
+        return Err(NamedColorsError::NoColors);
+    }
+
+    let (mut r, mut g, mut b) = (0.0, 0.0, 0.0);
+    for name in names {
+        let color = get_color(color_map, name).ok_or_else(|| NamedColorsError::ColorNotFound(name.to_string()))?;
+        let (lr, lg, lb) = color.to_linear();
+        r += lr;
+        g += lg;
+        b += lb;
+    }
+
+    let count = names.len() as f32;
+    Ok(Color::from_linear(r / count, g / count, b / count))
+}
+
+/// Finds the color of the map closest to the given RGB value, using the chosen metric.
+///
+/// Ties are broken by picking the alphabetically first name, so results are deterministic.
+///
+/// # Returns:
+/// * The name and RGB of the closest color, or `None` if the map is empty.
+pub fn nearest_color_with(color_map: &HashMap<String, Color>, r: u8, g: u8, b: u8, metric: DistanceMetric) -> Option<(String, (u8, u8, u8))> {
+    let target = Color::new(r, g, b);
+    color_map
+        .iter()
+        .map(|(name, color)| (metric_distance(&target, color, metric), name, color))
+        .min_by(|a, b| a.0.total_cmp(&b.0).then_with(|| a.1.cmp(b.1)))
+        .map(|(_, name, color)| (name.clone(), (color.r, color.g, color.b)))
+}
+
+/// Finds the closest color like [`nearest_color_with`], skipping the names in `exclude`, e.g.
+/// palette slots a quantizer has already used.
+///
+/// Excluded names are matched case-insensitively.
+///
+/// # Returns:
+/// * The name and RGB of the closest remaining color, or `None` if every color is excluded.
+pub fn nearest_color_excluding(
+    color_map: &HashMap<String, Color>,
+    r: u8,
+    g: u8,
+    b: u8,
+    exclude: &[&str],
+    metric: DistanceMetric,
+) -> Option<(String, (u8, u8, u8))> {
+    let excluded: HashSet<String> = exclude.iter().map(|name| fold_case(name)).collect();
+    let target = Color::new(r, g, b);
+    color_map
+        .iter()
+        .filter(|(name, _)| !excluded.contains(&fold_case(name)))
+        .map(|(name, color)| (metric_distance(&target, color, metric), name, color))
+        .min_by(|a, b| a.0.total_cmp(&b.0).then_with(|| a.1.cmp(b.1)))
+        .map(|(_, name, color)| (name.clone(), (color.r, color.g, color.b)))
+}
+
+/// Serde helpers to (de)serialize colors as `"#rrggbb"` strings instead of `{"r", "g", "b"}` objects.
+///
+/// ```rust
+/// use named_colors::colors::{hex_serde, Color};
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Theme {
+///     #[serde(with = "hex_serde")]
+///     accent: Color,
+/// }
+///
+/// let theme = Theme { accent: Color::new(255, 0, 0) };
+/// assert_eq!(serde_json::to_string(&theme).unwrap(), r##"{"accent":"#ff0000"}"##);
+/// ```
+pub mod hex_serde {
+    use super::Color;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::collections::HashMap;
+
+    /// Serializes a color as a hex string.
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&color.to_hex())
+    }
+
+    /// Deserializes a color from a hex string.
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        Color::from_hex(&hex).map_err(serde::de::Error::custom)
+    }
+
+    /// The same helpers for a whole map of colors, e.g. `#[serde(with = "hex_serde::map")]`.
+    pub mod map {
+        use super::*;
+        use serde::ser::SerializeMap;
+
+        /// Serializes every color of the map as a hex string.
+        pub fn serialize<S: Serializer>(color_map: &HashMap<String, Color>, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut map = serializer.serialize_map(Some(color_map.len()))?;
+            for (name, color) in color_map {
+                map.serialize_entry(name, &color.to_hex())?;
+            }
+            map.end()
+        }
+
+        /// Deserializes a map of hex strings into colors.
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<HashMap<String, Color>, D::Error> {
+            HashMap::<String, String>::deserialize(deserializer)?
+                .into_iter()
+                .map(|(name, hex)| Color::from_hex(&hex).map(|color| (name, color)).map_err(serde::de::Error::custom))
+                .collect()
+        }
+    }
+}
+
+/// Serde helpers accepting normalized float channels (`{"r": 1.0, "g": 0.5, "b": 0.0}`) as well as
+/// the usual `0..=255` integers.
+///
+/// Floats must be within `0.0..=1.0` and are scaled to `0..=255`; anything else, such as `1.5`, is an
+/// error rather than being clamped. Colors are always serialized with integer channels.
+///
+/// ```rust
+/// use named_colors::colors::{normalized_serde, Color};
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Theme {
+///     #[serde(with = "normalized_serde")]
+///     accent: Color,
+/// }
+///
+/// let theme: Theme = serde_json::from_str(r#"{"accent": {"r": 1.0, "g": 0.5, "b": 0.0}}"#).unwrap();
+/// assert_eq!(theme.accent, Color::new(255, 128, 0));
+/// ```
+pub mod normalized_serde {
+    use super::Color;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::HashMap;
+
+    /// A color given either with integer or normalized float channels.
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RawColor {
+        Bytes(Color),
+        Floats {
+            r: f64,
+            g: f64,
+            b: f64,
+            #[serde(default = "opaque")]
+            a: f64,
+        },
+    }
+
+    fn opaque() -> f64 {
+        1.0
+    }
+
+    impl RawColor {
+        fn into_color(self) -> Result<Color, String> {
+            match self {
+                RawColor::Bytes(color) => Ok(color),
+                RawColor::Floats { r, g, b, a } => {
+                    let scale = |channel: f64| {
+                        if (0.0..=1.0).contains(&channel) {
+                            Ok((channel * 255.0).round() as u8)
+                        } else {
+                            Err(format!("channel value {} is outside 0.0..=1.0", channel))
+                        }
+                    };
+                    Ok(Color::new_rgba(scale(r)?, scale(g)?, scale(b)?, scale(a)?))
+                }
+            }
+        }
+    }
+
+    /// Serializes a color with integer channels, like the default `Serialize` implementation.
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        color.serialize(serializer)
+    }
+
+    /// Deserializes a color with integer or normalized float channels.
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        RawColor::deserialize(deserializer)?.into_color().map_err(serde::de::Error::custom)
+    }
+
+    /// The same helpers for a whole map of colors, e.g. `#[serde(with = "normalized_serde::map")]`.
+    pub mod map {
+        use super::*;
+
+        /// Serializes every color of the map with integer channels.
+        pub fn serialize<S: Serializer>(color_map: &HashMap<String, Color>, serializer: S) -> Result<S::Ok, S::Error> {
+            color_map.serialize(serializer)
+        }
+
+        /// Deserializes a map of colors with integer or normalized float channels.
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<HashMap<String, Color>, D::Error> {
+            HashMap::<String, RawColor>::deserialize(deserializer)?
+                .into_iter()
+                .map(|(name, raw)| raw.into_color().map(|color| (name, color)).map_err(serde::de::Error::custom))
+                .collect()
+        }
+    }
+}
+
+/// Looks up several colors at once.
+///
+/// # Returns:
+/// * The RGB of each name in the same order as `names`, `None` for the names not found.
+pub fn get_colors_by_names(color_map: &HashMap<String, Color>, names: &[&str]) -> Vec<Option<(u8, u8, u8)>> {
+    names
+        .iter()
+        .map(|name| get_color(color_map, name).map(|color| (color.r, color.g, color.b)))
+        .collect()
+}
+
+/// Computes the Levenshtein edit distance between two strings.
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + if ca == *cb { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Looks up a color by name, suggesting the closest names when it is missing.
+///
+/// ```rust
+/// use named_colors::colors::{get_color_by_name_or_suggest, load_colors_from_file};
+///
+/// let colors = load_colors_from_file(r#"{"green": {"r": 0, "g": 255, "b": 0}}"#).unwrap();
+/// let err = get_color_by_name_or_suggest(&colors, "gren").unwrap_err();
+/// assert_eq!(err.to_string(), "color 'gren' not found, did you mean 'green'?");
+/// ```
+///
+/// # Returns:
+/// * `Err(NamedColorsError::UnknownColor)` with up to 3 of the closest names, at most 2 edits away.
+pub fn get_color_by_name_or_suggest(color_map: &HashMap<String, Color>, name: &str) -> Result<(u8, u8, u8), NamedColorsError> {
+    if let Some(color) = get_color(color_map, name) {
+        return Ok((color.r, color.g, color.b));
+    }
+
+    let normalized = normalize_name(name);
+    let mut candidates: Vec<(usize, &String)> = color_map
+        .keys()
+        .map(|key| (levenshtein(&normalized, key), key))
+        .filter(|(distance, _)| *distance <= 2)
+        .collect();
+    candidates.sort();
+
+    // Only keep the closest names, so "gren" suggests "green" but not "red"
+    let best = candidates.first().map(|(distance, _)| *distance);
+    Err(NamedColorsError::UnknownColor {
+        name: name.to_string(),
+        suggestions: candidates
+            .into_iter()
+            .filter(|(distance, _)| Some(*distance) == best)
+            .take(3)
+            .map(|(_, key)| key.clone())
+            .collect(),
+    })
+}
+
+/// Adds several colors under a common prefix, each stored as `"{prefix}_{name}"` (normalized).
+///
+/// Either every entry is added or none is: the whole batch is rejected if one of the prefixed
+/// names already exists or appears twice.
+///
+/// ```rust
+/// use named_colors::colors::add_colors_with_prefix;
+/// use std::collections::HashMap;
+///
+/// let mut colors = HashMap::new();
+/// add_colors_with_prefix(&mut colors, "brand", &[("blue", 0, 82, 204), ("red", 222, 53, 11)]).unwrap();
+/// assert!(colors.contains_key("brand_blue"));
+/// ```
+pub fn add_colors_with_prefix(color_map: &mut HashMap<String, Color>, prefix: &str, entries: &[(&str, u8, u8, u8)]) -> Result<(), NamedColorsError> {
+    let mut prepared: HashMap<String, Color> = HashMap::new();
+    for &(name, r, g, b) in entries {
+        add_color(&mut prepared, &format!("{}_{}", prefix, name), r, g, b)?;
+    }
+    if let Some(existing) = prepared.keys().find(|key| color_map.contains_key(*key)) {
+        return Err(NamedColorsError::DuplicateColor(existing.clone()));
+    }
+    color_map.extend(prepared);
+    Ok(())
+}
+
+/// Finds the known color names mentioned in free text, in order of appearance.
+///
+/// Matching is case-insensitive and on whole words only, so "redo" does not match "red".
+/// Names made of several words (e.g. `sky_blue`) are also found when written "sky blue" or
+/// "sky-blue", the longest name taking precedence.
+pub fn extract_colors_from_text(color_map: &HashMap<String, Color>, text: &str) -> Vec<(String, (u8, u8, u8))> {
+    const MAX_WORDS: usize = 3;
+
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect();
+
+    let mut found = Vec::new();
+    let mut i = 0;
+    while i < words.len() {
+        let matched = (1..=MAX_WORDS.min(words.len() - i)).rev().find_map(|count| {
+            let name = words[i..i + count].join("_");
+            color_map.get_key_value(&name).map(|(name, color)| (count, name, color))
+        });
+        match matched {
+            Some((count, name, color)) => {
+                found.push((name.clone(), (color.r, color.g, color.b)));
+                i += count;
+            }
+            None => i += 1,
+        }
+    }
+    found
+}
+
+/// Escapes the characters with a special meaning in XML/HTML text and attributes.
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Generates an Android `colors.xml` resource file from the map, sorted by name.
+///
+/// Android color resources are written `#AARRGGBB`: the alpha byte comes first (`FF` for opaque
+/// colors), followed by the red, green and blue bytes, in uppercase hex.
+///
+/// ```rust
+/// use named_colors::colors::{to_android_xml, Color};
+/// use std::collections::HashMap;
+///
+/// let colors = HashMap::from([("red".to_string(), Color::new(255, 0, 0))]);
+/// assert!(to_android_xml(&colors).contains(r##"<color name="red">#FFFF0000</color>"##));
+/// ```
+pub fn to_android_xml(color_map: &HashMap<String, Color>) -> String {
+    let mut names: Vec<&String> = color_map.keys().collect();
+    names.sort();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<resources>\n");
+    for name in names {
+        let color = &color_map[name];
+        xml.push_str(&format!(
+            "    <color name=\"{}\">#{:02X}{:02X}{:02X}{:02X}</color>\n",
+            escape_xml(name), color.a, color.r, color.g, color.b
+        ));
+    }
+    xml.push_str("</resources>\n");
+    xml
+}
+
+/// Merges the colors of `other` into `base`.
+///
+/// When a name exists in both maps, the color of `other` replaces the one of `base` only if
+/// `overwrite` is `true`.
+pub fn merge_colors(base: &mut HashMap<String, Color>, other: &HashMap<String, Color>, overwrite: bool) {
+    for (name, color) in other {
+        if overwrite || !base.contains_key(name) {
+            base.insert(name.clone(), *color);
+        }
+    }
+}
+
+/// Merges the colors of `other` into `base`, letting `resolver` pick the color of each name
+/// present in both maps.
+///
+/// The resolver is called with the name, the color of `base` and the color of `other`, and may
+/// return either of them or a new color, e.g. their average.
+///
+/// ```rust
+/// use named_colors::colors::{merge_colors_with, Color};
+/// use std::collections::HashMap;
+///
+/// let mut base = HashMap::from([("accent".to_string(), Color::new(200, 0, 0))]);
+/// let other = HashMap::from([("accent".to_string(), Color::new(100, 0, 0))]);
+/// merge_colors_with(&mut base, &other, |_, a, b| a.blend(b, 0.5));
+/// assert_eq!(base["accent"], Color::new(150, 0, 0));
+/// ```
+pub fn merge_colors_with<F: Fn(&str, &Color, &Color) -> Color>(base: &mut HashMap<String, Color>, other: &HashMap<String, Color>, resolver: F) {
+    for (name, color) in other {
+        match base.get_mut(name) {
+            Some(existing) => *existing = resolver(name, existing, color),
+            None => {
+                base.insert(name.clone(), *color);
+            }
+        }
+    }
+}
+
+/// A source of colors registered on a [`ColorLoader`].
+#[derive(Debug, Clone)]
+enum ColorSource {
+    Builtin,
+    File(PathBuf),
+    Json(String),
+}
+
+/// Loads and merges colors from several sources, e.g. the builtin colors overlaid with a user palette.
+///
+/// Sources are merged in the order they are added. By default a later source overrides the colors
+/// of the earlier ones; with `overwrite(false)` the first source defining a name wins instead.
+///
+/// ```rust
+/// use named_colors::colors::{Color, ColorLoader};
+///
+/// let colors = ColorLoader::new()
+///     .with_builtin()
+///     .with_json(r#"{"red": {"r": 200, "g": 0, "b": 0}}"#)
+///     .overwrite(true)
+///     .load()
+///     .unwrap();
+/// assert_eq!(colors["red"], Color::new(200, 0, 0));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ColorLoader {
+    sources: Vec<ColorSource>,
+    overwrite: bool,
+}
+
+impl Default for ColorLoader {
+    fn default() -> Self {
+        ColorLoader { sources: Vec::new(), overwrite: true }
+    }
+}
+
+impl ColorLoader {
+    /// Creates a loader without any source.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the builtin colors embedded in the crate.
+    pub fn with_builtin(mut self) -> Self {
+        self.sources.push(ColorSource::Builtin);
+        self
+    }
+
+    /// Adds a JSON palette file, read when [`ColorLoader::load`] is called.
+    pub fn with_file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.sources.push(ColorSource::File(path.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Adds a JSON palette given as a string.
+    pub fn with_json(mut self, json_data: &str) -> Self {
+        self.sources.push(ColorSource::Json(json_data.to_string()));
+        self
+    }
+
+    /// Sets whether later sources override the colors of earlier ones (`true` by default).
+    pub fn overwrite(mut self, overwrite: bool) -> Self {
+        self.overwrite = overwrite;
+        self
+    }
+
+    /// Loads every source in order and merges them into a single map.
+    ///
+    /// # Returns:
+    /// * The merged colors, or the error of the first source that failed to load.
+    pub fn load(&self) -> Result<HashMap<String, Color>, NamedColorsError> {
+        let mut colors = HashMap::new();
+        for source in &self.sources {
+            let loaded = match source {
+                ColorSource::Builtin => builtin_colors()?,
+                ColorSource::File(path) => load_colors_from_path(path)?,
+                ColorSource::Json(json_data) => load_colors_from_file(json_data)?,
+            };
+            merge_colors(&mut colors, &loaded, self.overwrite);
+        }
+        Ok(colors)
+    }
+}
+
+/// Checks whether a color name exists in the map (case-insensitive).
+pub fn has_color(color_map: &HashMap<String, Color>, name: &str) -> bool {
+    get_color(color_map, name).is_some()
+}
+
+/// Differences between two color maps, as returned by [`diff_color_maps`]. Every list is sorted by name.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ColorMapDiff {
+    /// Names only present in the first map.
+    pub only_in_a: Vec<String>,
+    /// Names only present in the second map.
+    pub only_in_b: Vec<String>,
+    /// Names present in both maps with different colors, with the color of each map.
+    pub changed: Vec<(String, Color, Color)>,
+}
+
+impl ColorMapDiff {
+    /// Returns `true` when both maps hold exactly the same colors.
+    pub fn is_empty(&self) -> bool {
+        self.only_in_a.is_empty() && self.only_in_b.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Compares two color maps, e.g. to review the changes made to a palette.
+pub fn diff_color_maps(a: &HashMap<String, Color>, b: &HashMap<String, Color>) -> ColorMapDiff {
+    let mut diff = ColorMapDiff::default();
+
+    for (name, color_a) in a {
+        match b.get(name) {
+            None => diff.only_in_a.push(name.clone()),
+            Some(color_b) if color_a != color_b => diff.changed.push((name.clone(), *color_a, *color_b)),
+            Some(_) => {}
+        }
+    }
+    diff.only_in_b = b.keys().filter(|name| !a.contains_key(*name)).cloned().collect();
+
+    diff.only_in_a.sort();
+    diff.only_in_b.sort();
+    diff.changed.sort_by(|x, y| x.0.cmp(&y.0));
+    diff
+}
+
+/// Checks whether two maps have the same names and colors, allowing each channel to differ by
+/// up to `tolerance` (see [`Color::approx_eq`]).
+///
+/// Useful to verify that a round trip through a lossy format didn't drift, where
+/// [`diff_color_maps`] would report every rounding difference.
+pub fn palettes_equivalent(a: &HashMap<String, Color>, b: &HashMap<String, Color>, tolerance: u8) -> bool {
+    a.len() == b.len() && a.iter().all(|(name, color)| b.get(name).is_some_and(|other| color.approx_eq(other, tolerance)))
+}
+
+/// Lists every pair of colors whose WCAG contrast ratio is at least `min_ratio`.
+///
+/// Each pair appears once, with its names in alphabetical order. The result is sorted by
+/// descending ratio, then by names. This compares every pair of the map, so the cost is O(n²).
+pub fn accessible_pairs(color_map: &HashMap<String, Color>, min_ratio: f32) -> Vec<(String, String, f32)> {
+    let mut entries: Vec<(&String, &Color)> = color_map.iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut pairs = Vec::new();
+    for (i, (name_a, color_a)) in entries.iter().enumerate() {
+        for (name_b, color_b) in &entries[i + 1..] {
+            let ratio = color_a.contrast_ratio(color_b);
+            if ratio >= min_ratio {
+                pairs.push((name_a.to_string(), name_b.to_string(), ratio));
+            }
+        }
+    }
+    pairs.sort_by(|a, b| b.2.total_cmp(&a.2).then_with(|| (&a.0, &a.1).cmp(&(&b.0, &b.1))));
+    pairs
+}
+
+/// Summarizes how varied a palette is as the average distance between all pairs of its colors;
+/// a low score flags a nearly monochromatic palette.
+///
+/// This compares every pair of the map, so the cost is O(n²); see [`palette_diversity_sampled`]
+/// for large maps.
+///
+/// # Returns:
+/// * The mean pairwise distance, in the units of `metric`, or `0.0` for fewer than two colors.
+pub fn palette_diversity(color_map: &HashMap<String, Color>, metric: DistanceMetric) -> f32 {
+    palette_diversity_sampled(color_map, metric, usize::MAX)
+}
+
+/// Estimates [`palette_diversity`] from at most `max_colors` colors, picked evenly across the
+/// names in alphabetical order so the result is deterministic.
+pub fn palette_diversity_sampled(color_map: &HashMap<String, Color>, metric: DistanceMetric, max_colors: usize) -> f32 {
+    let mut entries: Vec<(&String, &Color)> = color_map.iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    let step = (entries.len().max(1) - 1) / max_colors.max(1) + 1;
+    let sample: Vec<&Color> = entries.iter().step_by(step).map(|(_, color)| *color).collect();
+    if sample.len() < 2 {
+        return 0.0;
+    }
+
+    let mut total = 0.0;
+    for (i, a) in sample.iter().enumerate() {
+        for b in &sample[i + 1..] {
+            total += metric_distance(a, b, metric) as f64;
+        }
+    }
+    let pairs = sample.len() * (sample.len() - 1) / 2;
+    (total / pairs as f64) as f32
+}
+
+// Header of the binary color map format written by `to_bytes`
+const BINARY_MAGIC: &[u8; 4] = b"NCB2";
+// Header of the first version of the format, whose lengths are `u16`s; still accepted by `from_bytes`
+const BINARY_MAGIC_V1: &[u8; 4] = b"NCB1";
+
+/// Appends `value` as an unsigned LEB128 number: 7 bits per byte, high bit set on all but the last.
+fn write_leb128(bytes: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        bytes.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+/// Serializes a color map into a compact binary format, much faster to load than JSON.
+///
+/// The layout is the `NCB2` magic, the number of entries, then for each entry (sorted by name):
+/// the name length, the UTF-8 name, and the `r`, `g`, `b`, `a` bytes. The number of entries and
+/// the name lengths are unsigned LEB128 numbers, so names of any length are supported.
+pub fn to_bytes(color_map: &HashMap<String, Color>) -> Vec<u8> {
+    let mut names: Vec<&String> = color_map.keys().collect();
+    names.sort();
+
+    let mut bytes = Vec::with_capacity(8 + color_map.len() * 16);
+    bytes.extend_from_slice(BINARY_MAGIC);
+    write_leb128(&mut bytes, names.len());
+    for name in names {
+        let color = &color_map[name];
+        write_leb128(&mut bytes, name.len());
+        bytes.extend_from_slice(name.as_bytes());
+        bytes.extend_from_slice(&[color.r, color.g, color.b, color.a]);
+    }
+    bytes
+}
+
+/// Deserializes a color map written by [`to_bytes`].
+///
+/// Maps written in the older `NCB1` layout, with a little-endian `u32` entry count and `u16`
+/// name lengths, are read as well.
+///
+/// # Returns:
+/// * `Err(NamedColorsError::InvalidFormat)` if the data is truncated or not in this format.
+pub fn from_bytes(bytes: &[u8]) -> Result<HashMap<String, Color>, NamedColorsError> {
+    fn truncated() -> NamedColorsError {
+        NamedColorsError::InvalidFormat("truncated binary color map".to_string())
+    }
+
+    fn take<'a>(bytes: &mut &'a [u8], count: usize) -> Result<&'a [u8], NamedColorsError> {
+        if bytes.len() < count {
+            return Err(truncated());
+        }
+        let (head, tail) = bytes.split_at(count);
+        *bytes = tail;
+        Ok(head)
+    }
+
+    fn take_leb128(bytes: &mut &[u8]) -> Result<usize, NamedColorsError> {
+        let mut value = 0usize;
+        for shift in (0..usize::BITS).step_by(7) {
+            let byte = take(bytes, 1)?[0];
+            let bits = usize::from(byte & 0x7f);
+            if shift > 0 && bits >> (usize::BITS - shift) != 0 {
+                return Err(NamedColorsError::InvalidFormat("length too large in binary color map".to_string()));
+            }
+            value |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(NamedColorsError::InvalidFormat("length too large in binary color map".to_string()))
+    }
+
+    let mut rest = bytes;
+    let legacy = match take(&mut rest, 4)? {
+        magic if magic == BINARY_MAGIC => false,
+        magic if magic == BINARY_MAGIC_V1 => true,
+        _ => return Err(NamedColorsError::InvalidFormat("not a binary color map".to_string())),
+    };
+    let count = if legacy {
+        u32::from_le_bytes(take(&mut rest, 4)?.try_into().unwrap()) as usize
+    } else {
+        take_leb128(&mut rest)?
+    };
+
+    let mut colors = HashMap::with_capacity(count.min(rest.len()));
+    for _ in 0..count {
+        let length = if legacy {
+            u16::from_le_bytes(take(&mut rest, 2)?.try_into().unwrap()) as usize
+        } else {
+            take_leb128(&mut rest)?
+        };
+        let name = std::str::from_utf8(take(&mut rest, length)?)
+            .map_err(|_| NamedColorsError::InvalidFormat("color name is not valid UTF-8".to_string()))?;
+        let channels = take(&mut rest, 4)?;
+        colors.insert(name.to_string(), Color::new_rgba(channels[0], channels[1], channels[2], channels[3]));
+    }
+    if !rest.is_empty() {
+        return Err(NamedColorsError::InvalidFormat("trailing bytes after binary color map".to_string()));
+    }
+    Ok(colors)
+}
+
+/// Returns the colors whose HSL hue lies within `[min_deg, max_deg]`, sorted by name.
+///
+/// Both bounds are taken modulo 360, and a range with `min_deg > max_deg` wraps around 0°, so
+/// `colors_in_hue_range(&map, 350.0, 10.0)` selects the reds on both sides of 0°. Achromatic
+/// colors (grays), whose hue is undefined, are never included. A range spanning 360° or more,
+/// such as `(0.0, 360.0)`, selects every other color.
+pub fn colors_in_hue_range(color_map: &HashMap<String, Color>, min_deg: f32, max_deg: f32) -> Vec<(String, Color)> {
+    let full_circle = max_deg - min_deg >= 360.0;
+    let min = min_deg.rem_euclid(360.0);
+    let max = max_deg.rem_euclid(360.0);
+
+    let mut colors: Vec<(String, Color)> = color_map
+        .iter()
+        .filter(|(_, color)| {
+            let (h, s, _) = color.to_hsl();
+            s > 0.0 && (full_circle || if min <= max { h >= min && h <= max } else { h >= min || h <= max })
+        })
+        .map(|(name, color)| (name.clone(), *color))
+        .collect();
+    colors.sort_by(|a, b| a.0.cmp(&b.0));
+    colors
+}
+
+/// Generates a GIMP palette (`.gpl`) file, also understood by Inkscape and Krita, sorted by name.
+///
+/// After the `GIMP Palette` and `Name:` header lines, each color is written as its right-aligned
+/// decimal channels separated by spaces, then a tab and its name.
+pub fn to_gpl(color_map: &HashMap<String, Color>, palette_name: &str) -> String {
+    let mut names: Vec<&String> = color_map.keys().collect();
+    names.sort();
+
+    let mut gpl = format!("GIMP Palette\nName: {}\n#\n", palette_name);
+    for name in names {
+        let color = &color_map[name];
+        gpl.push_str(&format!("{:3} {:3} {:3}\t{}\n", color.r, color.g, color.b, name));
+    }
+    gpl
+}
+
+// Lowercase strict and reserved Rust keywords (all editions), which need the `r#` prefix to be
+// used as a module name; `crate`, `self` and `super` can't be raw identifiers and are handled apart
+const RUST_KEYWORDS: [&str; 48] = [
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn", "else", "enum", "extern",
+    "false", "final", "fn", "for", "gen", "if", "impl", "in", "let", "loop", "macro", "match", "mod", "move", "mut",
+    "override", "priv", "pub", "ref", "return", "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe",
+    "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// Turns a name into a Rust identifier made of `[A-Za-z0-9_]`, not starting with a digit.
+fn rust_identifier(name: &str, uppercase: bool) -> String {
+    let mut identifier = String::new();
+    for c in name.trim().chars() {
+        if c.is_ascii_alphanumeric() {
+            identifier.push(if uppercase { c.to_ascii_uppercase() } else { c.to_ascii_lowercase() });
+        } else if !identifier.ends_with('_') {
+            identifier.push('_');
+        }
+    }
+    let identifier = identifier.trim_matches('_');
+    if identifier.is_empty() {
+        if uppercase { "UNNAMED" } else { "unnamed" }.to_string()
+    } else if identifier.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", identifier)
+    } else if !uppercase && matches!(identifier, "crate" | "self" | "super") {
+        // These can't be raw identifiers
+        format!("{}_", identifier)
+    } else if !uppercase && RUST_KEYWORDS.contains(&identifier) {
+        format!("r#{}", identifier)
+    } else {
+        identifier.to_string()
+    }
+}
+
+/// Generates a Rust module of `Color` constants, one per color sorted by name, e.g. from a build
+/// script to bake a palette into a binary:
+///
+/// ```text
+/// pub mod palette {
+///     use named_colors::colors::Color;
+///
+///     pub const SKY_BLUE: Color = Color { r: 135, g: 206, b: 235, a: 255 };
+/// }
+/// ```
+///
+/// Names and `module_name` are turned into identifiers: characters other than ASCII letters and
+/// digits become underscores, a leading digit gets an underscore prefix and a keyword module
+/// name the `r#` prefix. Names that end up
+/// identical get a numeric suffix (`SKY_BLUE_2`), so the output always compiles.
+pub fn to_rust_source(color_map: &HashMap<String, Color>, module_name: &str) -> String {
+    let mut names: Vec<&String> = color_map.keys().collect();
+    names.sort();
+
+    let mut source = format!("pub mod {} {{\n    use named_colors::colors::Color;\n\n", rust_identifier(module_name, false));
+    let mut used = HashSet::new();
+    for name in names {
+        let base = rust_identifier(name, true);
+        let mut identifier = base.clone();
+        let mut suffix = 2;
+        while !used.insert(identifier.clone()) {
+            identifier = format!("{}_{}", base, suffix);
+            suffix += 1;
+        }
+        let color = &color_map[name];
+        source.push_str(&format!(
+            "    pub const {}: Color = Color {{ r: {}, g: {}, b: {}, a: {} }};\n",
+            identifier, color.r, color.g, color.b, color.a
+        ));
+    }
+    source.push_str("}\n");
+    source
+}
+
+/// Generates an Adobe Swatch Exchange (`.ase`) file, as imported by Illustrator and Photoshop,
+/// with one RGB swatch per color, sorted by name.
+///
+/// The file is the `ASEF` signature, version 1.0 and the block count, followed by one color block
+/// per swatch. All values are big-endian; names are UTF-16 with a terminating null.
+pub fn to_ase(color_map: &HashMap<String, Color>) -> Vec<u8> {
+    let mut names: Vec<&String> = color_map.keys().collect();
+    names.sort();
+
+    let mut ase = Vec::new();
+    ase.extend_from_slice(b"ASEF");
+    ase.extend_from_slice(&1u16.to_be_bytes());
+    ase.extend_from_slice(&0u16.to_be_bytes());
+    ase.extend_from_slice(&(names.len() as u32).to_be_bytes());
+
+    for name in names {
+        let color = &color_map[name];
+        let mut name_units: Vec<u16> = name.encode_utf16().collect();
+        name_units.push(0);
+
+        let mut block = Vec::new();
+        block.extend_from_slice(&(name_units.len() as u16).to_be_bytes());
+        for unit in name_units {
+            block.extend_from_slice(&unit.to_be_bytes());
+        }
+        block.extend_from_slice(b"RGB ");
+        let (r, g, b) = color.to_f32();
+        for channel in [r, g, b] {
+            block.extend_from_slice(&channel.to_be_bytes());
+        }
+        // Color type: 0 = global, 1 = spot, 2 = normal
+        block.extend_from_slice(&2u16.to_be_bytes());
+
+        ase.extend_from_slice(&1u16.to_be_bytes());
+        ase.extend_from_slice(&(block.len() as u32).to_be_bytes());
+        ase.extend_from_slice(&block);
+    }
+    ase
+}
+
+/// Parses a command-line color argument: a builtin color name, a hex code or an `rgb()` string.
+///
+/// Its signature fits clap's `value_parser`, so `--color red` or `--color '#ff0000'` just work:
+///
+/// ```rust,ignore
+/// #[arg(long, value_parser = named_colors::colors::parse_color_arg)]
+/// color: Color,
+/// ```
+#[cfg(feature = "clap")]
+pub fn parse_color_arg(s: &str) -> Result<Color, String> {
+    let spec = s.trim();
+    if let Some(color) = builtin_colors().ok().as_ref().and_then(|colors| get_color(colors, spec)) {
+        return Ok(*color);
+    }
+    if spec.to_ascii_lowercase().starts_with("rgb") {
+        return Color::from_rgb_str(spec).map_err(|err| err.to_string());
+    }
+    Color::from_hex(spec).map_err(|_| format!("'{}' is not a known color name, a hex code (#rrggbb) or an rgb() value", s))
+}
+
+/// Mixes colors with the given weights, in linear space.
+///
+/// The weights are normalized, so `[(red, 2.0), (blue, 1.0)]` is two thirds red and one third blue.
+///
+/// # Returns:
+/// * `Err(NamedColorsError::NoColors)` if `colors` is empty.
+/// * `Err(NamedColorsError::InvalidWeights)` if a weight is negative (or NaN) or they all are zero.
+pub fn mix_weighted(colors: &[(Color, f32)]) -> Result<Color, NamedColorsError> {
+    if colors.is_empty() {
+        return Err(NamedColorsError::NoColors);
+    }
+    if colors.iter().any(|(_, weight)| weight.is_nan() || *weight < 0.0) {
+        return Err(NamedColorsError::InvalidWeights);
+    }
+    let total: f32 = colors.iter().map(|(_, weight)| weight).sum();
+    if total <= 0.0 {
+        return Err(NamedColorsError::InvalidWeights);
+    }
+
+    let (mut r, mut g, mut b) = (0.0, 0.0, 0.0);
+    for (color, weight) in colors {
+        let (lr, lg, lb) = color.to_linear();
+        r += lr * weight / total;
+        g += lg * weight / total;
+        b += lb * weight / total;
+    }
+    Ok(Color::from_linear(r, g, b))
+}
+
+/// Serializes a color map to pretty-printed JSON with the names in alphabetical order.
+///
+/// `HashMap` iteration order changes from run to run, so serializing it directly produces noisy
+/// diffs for palettes kept under version control; this output is stable for the same colors.
+///
+/// ```rust
+/// use std::collections::HashMap;
+/// use named_colors::colors::{save_colors_to_string_sorted, Color};
+///
+/// let colors = HashMap::from([("red".to_string(), Color::new(255, 0, 0))]);
+/// let json = save_colors_to_string_sorted(&colors).unwrap();
+/// assert!(json.contains("\"red\""));
+/// ```
+pub fn save_colors_to_string_sorted(color_map: &HashMap<String, Color>) -> Result<String, NamedColorsError> {
+    let sorted: BTreeMap<&String, &Color> = color_map.iter().collect();
+    Ok(serde_json::to_string_pretty(&sorted)?)
+}
+
+/// Writes a color map as pretty-printed JSON straight to a file, socket or any other writer,
+/// without building the whole string first. Names are sorted like [`save_colors_to_string_sorted`].
+///
+/// # Returns:
+/// * `Err(NamedColorsError::Io)` if writing fails.
+pub fn write_colors_json<W: Write>(color_map: &HashMap<String, Color>, writer: W) -> Result<(), NamedColorsError> {
+    let sorted: BTreeMap<&String, &Color> = color_map.iter().collect();
+    serde_json::to_writer_pretty(writer, &sorted).map_err(|err| {
+        if err.is_io() {
+            NamedColorsError::Io(err.into())
+        } else {
+            NamedColorsError::ParseError(err)
+        }
+    })
+}
+
+/// Ranks every color of the map by its distance to `reference`, closest first.
+///
+/// Colors at the same distance are ordered by name, so results are deterministic.
+///
+/// # Returns:
+/// * Every entry as `(name, color, distance)`, sorted by ascending distance.
+pub fn sort_by_similarity(color_map: &HashMap<String, Color>, reference: &Color, metric: DistanceMetric) -> Vec<(String, Color, f32)> {
+    let mut ranked: Vec<(String, Color, f32)> = color_map
+        .iter()
+        .map(|(name, color)| (name.clone(), *color, reference.distance(color, metric)))
+        .collect();
+    ranked.sort_by(|a, b| a.2.total_cmp(&b.2).then_with(|| a.0.cmp(&b.0)));
+    ranked
+}
+
+/// Returns the aliases registered with [`add_alias`], from normalized alias to target name.
+fn alias_registry() -> &'static RwLock<HashMap<String, String>> {
+    static ALIASES: OnceLock<RwLock<HashMap<String, String>>> = OnceLock::new();
+
+    ALIASES.get_or_init(Default::default)
+}
+
+/// Makes `alias` (normalized, see [`normalize_name`]) resolve to the existing color `existing`,
+/// such as `grey` for `gray`.
+///
+/// Aliases are registered for the whole process and are seen by [`get_color`], every lookup
+/// built on it, and [`crate::get_color_by_name`]. They are stored as an indirection to the target
+/// name rather than a copy of its RGB, so an alias always resolves to the target's current value
+/// even after the map is updated. Aliasing another alias points directly to its target.
+///
+/// ```rust
+/// use named_colors::colors::{add_alias, builtin_colors, get_color};
+///
+/// let colors = builtin_colors().unwrap();
+/// add_alias(&colors, "grey", "gray").unwrap();
+/// assert_eq!(get_color(&colors, "Grey"), colors.get("gray"));
+/// ```
+///
+/// # Returns:
+/// * `Err(NamedColorsError::ColorNotFound)` if `existing` is neither a color of the map nor an alias.
+/// * `Err(NamedColorsError::DuplicateColor)` if `alias` is already a color or an alias.
+/// * `Err(NamedColorsError::InvalidName)` if the alias is empty once normalized.
+pub fn add_alias(color_map: &HashMap<String, Color>, alias: &str, existing: &str) -> Result<(), NamedColorsError> {
+    let mut aliases = alias_registry().write().unwrap_or_else(PoisonError::into_inner);
+
+    let normalized = normalize_name(existing);
+    let target = if color_map.contains_key(existing) {
+        existing.to_string()
+    } else if color_map.contains_key(&normalized) {
+        normalized
+    } else if let Some(target) = aliases.get(&normalized) {
+        target.clone()
+    } else {
+        return Err(NamedColorsError::ColorNotFound(existing.to_string()));
+    };
+
+    let normalized_alias = normalize_name(alias);
+    if normalized_alias.is_empty() {
+        return Err(NamedColorsError::InvalidName(alias.to_string()));
+    }
+    if color_map.contains_key(alias) || color_map.contains_key(&normalized_alias) || aliases.contains_key(&normalized_alias) {
+        return Err(NamedColorsError::DuplicateColor(normalized_alias));
+    }
+    aliases.insert(normalized_alias, target);
+    Ok(())
+}
+
+/// Returns the name of the color an alias registered with [`add_alias`] points to, if `alias` is one.
+pub fn alias_target(alias: &str) -> Option<String> {
+    let aliases = alias_registry().read().unwrap_or_else(PoisonError::into_inner);
+    aliases.get(&normalize_name(alias)).cloned()
+}
+
+/// Scores how warm or cool a set of colors feels, from `-1.0` (cool) to `1.0` (warm).
+///
+/// Each hue scores `cos(hue - 30°)`, so orange is the warmest and azure the coolest, scaled by the
+/// HSV saturation of the color; the scores are then averaged, so grays pull the result towards `0.0`.
+///
+/// # Returns:
+/// * `Err(NamedColorsError::ColorNotFound)` with the first name missing from the map.
+/// * `Err(NamedColorsError::NoColors)` if `names` is empty.
+pub fn palette_warmth(color_map: &HashMap<String, Color>, names: &[&str]) -> Result<f32, NamedColorsError> {
+    if names.is_empty() {
+        return Err(NamedColorsError::NoColors);
+    }
+
+    let mut total = 0.0;
+    for name in names {
+        let color = get_color(color_map, name).ok_or_else(|| NamedColorsError::ColorNotFound(name.to_string()))?;
+        let (hue, saturation, _) = color.to_hsv();
+        total += (hue - 30.0).to_radians().cos() * saturation;
+    }
+    Ok(total / names.len() as f32)
+}
+
+/// Builds a color map from one of the CSS color tables.
+fn css_color_map(table: &[(&str, u8, u8, u8)]) -> HashMap<String, Color> {
+    table.iter().map(|&(name, r, g, b)| (name.to_string(), Color::new(r, g, b))).collect()
+}
+
+/// Returns the 16 basic CSS color keywords (`black`, `fuchsia`, `aqua`, ...) with their CSS values.
+///
+/// Useful when targeting renderers that only understand the basic keywords. The values follow the
+/// CSS specification, which differs from the builtin palette for some names (CSS `green` is `#008000`).
+pub fn css_basic_colors() -> HashMap<String, Color> {
+    css_color_map(&crate::css::CSS_BASIC_COLORS)
+}
+
+/// Returns every CSS named color: the 147 extended (X11) keywords, basic ones included, and `rebeccapurple`.
+pub fn css_extended_colors() -> HashMap<String, Color> {
+    css_color_map(&crate::css::CSS_EXTENDED_COLORS)
+}
+
+/// Collects the colors of a CSS declaration value, looking inside other functions such as
+/// `linear-gradient()` too.
+fn scan_css_value(value: &str, keywords: &HashMap<String, Color>, found: &mut Vec<Color>) {
+    let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+    let mut rest = value;
+    while let Some(c) = rest.chars().next() {
+        if c == '#' {
+            let len = rest[1..].find(|c: char| !c.is_ascii_hexdigit()).unwrap_or(rest.len() - 1);
+            let ends_token = !rest[1 + len..].starts_with(is_ident);
+            if ends_token && matches!(len, 3 | 4 | 6 | 8) {
+                found.extend(Color::from_hex(&rest[..1 + len]).ok());
+            }
+            rest = rest[1 + len..].trim_start_matches(is_ident);
+        } else if c.is_ascii_alphabetic() || c == '-' {
+            let len = rest.find(|c: char| !is_ident(c)).unwrap_or(rest.len());
+            let ident = rest[..len].to_ascii_lowercase();
+            if rest[len..].starts_with('(') && matches!(ident.as_str(), "rgb" | "rgba" | "hsl") {
+                let end = rest.find(')').map_or(rest.len(), |i| i + 1);
+                let function = &rest[..end];
+                let color = if ident == "hsl" { Color::from_hsl_str(function) } else { Color::from_rgb_str(function) };
+                found.extend(color.ok());
+                rest = &rest[end..];
+            } else {
+                found.extend(keywords.get(&ident).copied());
+                rest = &rest[len..];
+            }
+        } else if c.is_ascii_digit() || c == '.' {
+            // Numbers and their units, so `10px` isn't taken for anything else
+            rest = rest.trim_start_matches(|c: char| is_ident(c) || c == '.' || c == '%');
+        } else {
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+}
+
+/// Lists every color used in a CSS stylesheet, in order of appearance and with repeats, e.g. to
+/// audit the colors of a design.
+///
+/// Hex codes, `rgb()`, `rgba()` and `hsl()` functions and CSS color keywords are recognized in
+/// declaration values only, so selectors such as `.red` or `#fade` and comments are ignored.
+///
+/// ```rust
+/// use named_colors::colors::{extract_colors_from_css, Color};
+///
+/// let colors = extract_colors_from_css("a { color: teal; border: 1px solid #ccc; }");
+/// assert_eq!(colors, vec![Color::new(0, 128, 128), Color::new(204, 204, 204)]);
+/// ```
+pub fn extract_colors_from_css(css: &str) -> Vec<Color> {
+    let keywords = css_extended_colors();
+    let mut found = Vec::new();
+
+    let mut depth = 0usize;
+    let mut value_start = None;
+    let mut rest = css;
+    let mut offset = 0;
+    while let Some(c) = rest.chars().next() {
+        if rest.starts_with("/*") {
+            let end = rest.find("*/").map_or(rest.len(), |i| i + 2);
+            if let Some(start) = value_start.take() {
+                scan_css_value(&css[start..offset], &keywords, &mut found);
+                value_start = Some(offset + end);
+            }
+            rest = &rest[end..];
+            offset += end;
+            continue;
+        }
+        match c {
+            '{' | '}' | ';' => {
+                if let Some(start) = value_start.take() {
+                    scan_css_value(&css[start..offset], &keywords, &mut found);
+                }
+                match c {
+                    '{' => depth += 1,
+                    '}' => depth = depth.saturating_sub(1),
+                    _ => {}
+                }
+            }
+            ':' if depth > 0 && value_start.is_none() => value_start = Some(offset + 1),
+            _ => {}
+        }
+        rest = &rest[c.len_utf8()..];
+        offset += c.len_utf8();
+    }
+    if let Some(start) = value_start {
+        scan_css_value(&css[start..], &keywords, &mut found);
+    }
+    found
+}
+
+/// A color map that iterates in name order.
+///
+/// The free functions of this module work on `HashMap<String, Color>`; `ColorMap` is for code that
+/// walks the colors and wants a stable order, and converts from and to a `HashMap`.
+///
+/// ```rust
+/// use named_colors::colors::{Color, ColorMap};
+///
+/// let map: ColorMap = vec![("red".to_string(), Color::new(255, 0, 0)), ("navy".to_string(), Color::new(0, 0, 128))]
+///     .into_iter()
+///     .collect();
+/// for (name, color) in &map {
+///     println!("{}: {}", name, color.to_hex());  // navy first, then red
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ColorMap {
+    colors: BTreeMap<String, Color>,
+}
+
+impl ColorMap {
+    /// Creates an empty map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of colors.
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    /// Checks whether the map has no colors.
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+
+    /// Looks up a color by its exact name, then by its normalized name (see [`normalize_name`]).
+    pub fn get(&self, name: &str) -> Option<&Color> {
+        self.colors.get(name).or_else(|| self.colors.get(&normalize_name(name)))
+    }
+
+    /// Inserts a color under `name` as is, returning the color it replaced.
+    pub fn insert(&mut self, name: String, color: Color) -> Option<Color> {
+        self.colors.insert(name, color)
+    }
+
+    /// Iterates over the colors as `(name, color)` in alphabetical order of the names.
+    pub fn iter(&self) -> ColorMapIter<'_> {
+        ColorMapIter { inner: self.colors.iter() }
+    }
+
+    /// Converts the map into the `HashMap` taken by the free functions of this module.
+    pub fn into_hash_map(self) -> HashMap<String, Color> {
+        self.colors.into_iter().collect()
+    }
+}
+
+/// Borrowing iterator over a [`ColorMap`], created by [`ColorMap::iter`].
+#[derive(Debug, Clone)]
+pub struct ColorMapIter<'a> {
+    inner: btree_map::Iter<'a, String, Color>,
+}
+
+impl<'a> Iterator for ColorMapIter<'a> {
+    type Item = (&'a str, &'a Color);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(name, color)| (name.as_str(), color))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a> IntoIterator for &'a ColorMap {
+    type Item = (&'a str, &'a Color);
+    type IntoIter = ColorMapIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl IntoIterator for ColorMap {
+    type Item = (String, Color);
+    type IntoIter = btree_map::IntoIter<String, Color>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.colors.into_iter()
+    }
+}
+
+impl FromIterator<(String, Color)> for ColorMap {
+    fn from_iter<I: IntoIterator<Item = (String, Color)>>(iter: I) -> Self {
+        ColorMap { colors: iter.into_iter().collect() }
+    }
+}
+
+impl From<HashMap<String, Color>> for ColorMap {
+    fn from(colors: HashMap<String, Color>) -> Self {
+        colors.into_iter().collect()
+    }
+}
+
+/// Snaps a color to the nearest entry of a user-supplied palette, e.g. a fixed 16-color pixel-art palette.
+///
+/// Ties are broken by picking the alphabetically first name, like [`nearest_color_with`].
+///
+/// # Returns:
+/// * The name and color of the closest palette entry, or `None` if the palette is empty.
+pub fn map_to_palette(target: &HashMap<String, Color>, color: &Color, metric: DistanceMetric) -> Option<(String, Color)> {
+    target
+        .iter()
+        .map(|(name, entry)| (color.distance(entry, metric), name, entry))
+        .min_by(|a, b| a.0.total_cmp(&b.0).then_with(|| a.1.cmp(b.1)))
+        .map(|(_, name, entry)| (name.clone(), *entry))
+}
+
+/// Finds pairs of colors that are within `threshold` of each other, e.g. to merge indistinguishable entries.
+///
+/// Every pair is compared, so this is O(n²) in the size of the map. A small threshold in ΔE units
+/// (around `2.0` with [`DistanceMetric::CIE76`]) catches colors that look the same.
+///
+/// # Returns:
+/// * `(name_a, name_b, distance)` with `name_a < name_b`, sorted by distance then names.
+pub fn near_duplicates(color_map: &HashMap<String, Color>, threshold: f32, metric: DistanceMetric) -> Vec<(String, String, f32)> {
+    let mut entries: Vec<(&String, &Color)> = color_map.iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut pairs = Vec::new();
+    for (i, (name_a, color_a)) in entries.iter().enumerate() {
+        for (name_b, color_b) in &entries[i + 1..] {
+            let distance = color_a.distance(color_b, metric);
+            if distance <= threshold {
+                pairs.push((name_a.to_string(), name_b.to_string(), distance));
+            }
+        }
+    }
+    pairs.sort_by(|a, b| a.2.total_cmp(&b.2).then_with(|| (&a.0, &a.1).cmp(&(&b.0, &b.1))));
+    pairs
+}
+
+/// Lists the colors as `(name, "#rrggbb")` pairs sorted by name, the shape templates and tables expect.
+pub fn to_name_hex_pairs(color_map: &HashMap<String, Color>) -> Vec<(String, String)> {
+    let mut pairs: Vec<(String, String)> = color_map.iter().map(|(name, color)| (name.clone(), color.to_hex())).collect();
+    pairs.sort();
+    pairs
+}
+
+/// Consumes the map and returns its entries sorted by name, moving the names instead of cloning
+/// them when the map isn't needed anymore.
+pub fn into_sorted_vec(color_map: HashMap<String, Color>) -> Vec<(String, Color)> {
+    let mut entries: Vec<(String, Color)> = color_map.into_iter().collect();
+    entries.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    entries
+}
+
+/// A candidate of [`nearest_colors`], ordered by distance then name so the heap keeps the worst on top.
+struct Candidate<'a> {
+    distance: f32,
+    name: &'a String,
+    color: &'a Color,
+}
+
+impl PartialEq for Candidate<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == std::cmp::Ordering::Equal
+    }
+}
+
+impl Eq for Candidate<'_> {}
+
+impl PartialOrd for Candidate<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate<'_> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.distance.total_cmp(&other.distance).then_with(|| self.name.cmp(other.name))
+    }
+}
+
+/// Finds the `n` colors of the map closest to the given RGB value, using the chosen metric.
+///
+/// Only the `n` best candidates are kept in a bounded heap while scanning, so large maps aren't
+/// sorted in full. Ties are broken by name, like [`nearest_color_with`].
+///
+/// # Returns:
+/// * Up to `n` entries as `(name, rgb, distance)`, sorted by ascending distance.
+pub fn nearest_colors(color_map: &HashMap<String, Color>, r: u8, g: u8, b: u8, n: usize, metric: DistanceMetric) -> Vec<(String, (u8, u8, u8), f32)> {
+    if n == 0 {
+        return Vec::new();
+    }
+
+    let target = Color::new(r, g, b);
+    let mut heap = BinaryHeap::with_capacity(n + 1);
+    for (name, color) in color_map {
+        heap.push(Candidate { distance: target.distance(color, metric), name, color });
+        if heap.len() > n {
+            heap.pop();
+        }
+    }
+
+    heap.into_sorted_vec()
+        .into_iter()
+        .map(|candidate| (candidate.name.clone(), (candidate.color.r, candidate.color.g, candidate.color.b), candidate.distance))
+        .collect()
+}
+
+/// Resolves one color token: a name of the map, an `rgb()`/`hsl()` function or a hex code.
+fn resolve_color_token(color_map: &HashMap<String, Color>, token: &str) -> Result<Color, NamedColorsError> {
+    if let Some(color) = get_color(color_map, token) {
+        return Ok(*color);
+    }
+    let lower = token.to_ascii_lowercase();
+    if lower.starts_with("rgb") {
+        Color::from_rgb_str(token)
+    } else if lower.starts_with("hsl") {
+        Color::from_hsl_str(token)
+    } else if token.starts_with('#') {
+        Color::from_hex(token)
+    } else {
+        // Bare hex codes such as `ff0000` are accepted too, anything else is an unknown name
+        Color::from_hex(token).map_err(|_| NamedColorsError::ColorNotFound(token.to_string()))
+    }
+}
+
+/// Parses a comma- or whitespace-separated list of colors, such as `"red, #00ff00 rgb(0, 0, 255)"`.
+///
+/// Each token is a name of the map, a hex code or an `rgb()`/`hsl()` function (whose own commas
+/// and spaces don't split it), and resolves independently so one bad token doesn't fail the others.
+///
+/// # Returns:
+/// * One result per token, in input order.
+pub fn parse_color_list(color_map: &HashMap<String, Color>, input: &str) -> Vec<Result<Color, NamedColorsError>> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    let mut depth = 0usize;
+    for (i, c) in input.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        let separator = depth == 0 && (c == ',' || c.is_whitespace());
+        match (separator, start) {
+            (true, Some(begin)) => {
+                tokens.push(&input[begin..i]);
+                start = None;
+            }
+            (false, None) => start = Some(i),
+            _ => {}
+        }
+    }
+    if let Some(begin) = start {
+        tokens.push(&input[begin..]);
+    }
+
+    tokens.into_iter().map(|token| resolve_color_token(color_map, token)).collect()
+}
+
+/// Turns whatever a user typed into a color: a builtin color name, a hex code, or an
+/// `rgb()`/`hsl()` function, tried in this order.
+///
+/// The builtin colors are parsed once and shared (see [`shared_builtin`]).
+///
+/// ```rust
+/// use named_colors::colors::{resolve, Color};
+///
+/// assert_eq!(resolve("DarkOrange").unwrap(), Color::new(255, 140, 0));
+/// assert_eq!(resolve("hsl(120, 100%, 50%)").unwrap(), Color::new(0, 255, 0));
+/// ```
+///
+/// # Returns:
+/// * `Err(NamedColorsError::ColorNotFound)` if `spec` is neither a builtin name nor a hex code.
+/// * `Err(NamedColorsError::InvalidFormat)` (or `InvalidHex`) if it looks like a function (or a
+///   `#` code) but is malformed.
+pub fn resolve(spec: &str) -> Result<Color, NamedColorsError> {
+    resolve_color_token(&shared_builtin(), spec.trim())
+}
+
+/// Averages every color of the map in linear space, like [`average_color`] over all the names.
+///
+/// # Returns:
+/// * The mean color, or `None` if the map is empty.
+pub fn palette_average(color_map: &HashMap<String, Color>) -> Option<Color> {
+    let weighted: Vec<(Color, f32)> = color_map.values().map(|color| (*color, 1.0)).collect();
+    mix_weighted(&weighted).ok()
+}
+
+/// Computes the bounding box of the palette in RGB space, e.g. to see how spread out it is or to
+/// normalize its colors.
+///
+/// # Returns:
+/// * `(min, max)` holding the lowest and highest value of each channel across the map, or `None`
+///   if the map is empty. Both are opaque and usually not colors of the map.
+pub fn palette_bounds(color_map: &HashMap<String, Color>) -> Option<(Color, Color)> {
+    let mut colors = color_map.values();
+    let first = *colors.next()?;
+    Some(colors.fold((first.with_alpha(255), first.with_alpha(255)), |(min, max), color| {
+        (
+            Color::new(min.r.min(color.r), min.g.min(color.g), min.b.min(color.b)),
+            Color::new(max.r.max(color.r), max.g.max(color.g), max.b.max(color.b)),
+        )
+    }))
+}
+
+/// Moves a color to a new name, keeping its value.
+///
+/// `old` is looked up like [`get_color`]; `new` is normalized like in [`add_color`]. Renaming a
+/// color to the name it already has is a no-op.
+///
+/// # Returns:
+/// * `Err(NamedColorsError::ColorNotFound)` if `old` is not in the map.
+/// * `Err(NamedColorsError::DuplicateColor)` if another color already uses the new name.
+/// * `Err(NamedColorsError::InvalidName)` if the new name is empty once normalized.
+pub fn rename_color(color_map: &mut HashMap<String, Color>, old: &str, new: &str) -> Result<(), NamedColorsError> {
+    let old_key = if color_map.contains_key(old) {
+        old.to_string()
+    } else {
+        let normalized = normalize_name(old);
+        if !color_map.contains_key(&normalized) {
+            return Err(NamedColorsError::ColorNotFound(old.to_string()));
+        }
+        normalized
+    };
+
+    let new_key = normalize_name(new);
+    if new_key.is_empty() {
+        return Err(NamedColorsError::InvalidName(new.to_string()));
+    }
+    if new_key == old_key {
+        return Ok(());
+    }
+    if color_map.contains_key(&new_key) {
+        return Err(NamedColorsError::DuplicateColor(new_key));
+    }
+
+    let color = color_map.remove(&old_key).expect("the old key was just found");
+    color_map.insert(new_key, color);
+    Ok(())
+}
+
+/// Returns one page of the colors sorted by name, pages being numbered from `0`.
+///
+/// # Returns:
+/// * Up to `per_page` entries; the last page may be shorter, and out-of-range pages (or a
+///   `per_page` of `0`) give an empty vector.
+pub fn paginate_colors(color_map: &HashMap<String, Color>, page: usize, per_page: usize) -> Vec<(String, Color)> {
+    let mut entries: Vec<(&String, &Color)> = color_map.iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    entries
+        .into_iter()
+        .skip(page.saturating_mul(per_page))
+        .take(per_page)
+        .map(|(name, color)| (name.clone(), *color))
+        .collect()
+}
+
+/// Splits the colors into light and dark ones according to [`Color::is_light`].
+///
+/// # Returns:
+/// * `(light, dark)`: light colors suit light-mode surfaces, dark ones dark-mode backgrounds.
+pub fn split_light_dark(color_map: &HashMap<String, Color>) -> (HashMap<String, Color>, HashMap<String, Color>) {
+    color_map.iter().map(|(name, color)| (name.clone(), *color)).partition(|(_, color)| color.is_light())
+}
+
+/// Generates a self-contained HTML snippet previewing the palette: one swatch per color, sorted
+/// by name, showing the name and hex code in black or white (see [`Color::best_text_color`]).
+///
+/// ```rust
+/// use named_colors::colors::{to_html_preview, Color};
+/// use std::collections::HashMap;
+///
+/// let colors = HashMap::from([("red".to_string(), Color::new(255, 0, 0))]);
+/// assert!(to_html_preview(&colors).contains("background: #ff0000"));
+/// ```
+pub fn to_html_preview(color_map: &HashMap<String, Color>) -> String {
+    let mut names: Vec<&String> = color_map.keys().collect();
+    names.sort();
+
+    let mut html = String::from("<div style=\"display: flex; flex-wrap: wrap; gap: 8px; font-family: sans-serif;\">\n");
+    for name in names {
+        let color = &color_map[name];
+        html.push_str(&format!(
+            "  <div style=\"background: {}; color: {}; width: 120px; padding: 24px 8px; border-radius: 4px;\">{}<br>{}</div>\n",
+            color.to_hex(), color.best_text_color().to_hex(), escape_xml(name), color.to_hex()
+        ));
+    }
+    html.push_str("</div>\n");
+    html
+}
+
+/// A k-d tree over the RGB values of a color map, for repeated nearest-color queries.
+///
+/// Building costs O(n log² n) once; each [`NearestIndex::query`] then only visits a few entries
+/// instead of scanning the whole map. Distances are Euclidean in RGB, and results are the same as
+/// [`nearest_color_with`] with [`DistanceMetric::Euclidean`], ties included.
+///
+/// ```rust
+/// use named_colors::colors::{builtin_colors, NearestIndex};
+///
+/// let index = NearestIndex::build(&builtin_colors().unwrap());
+/// assert_eq!(index.query(250, 5, 5), Some(("red".to_string(), (255, 0, 0))));
+/// ```
+#[derive(Debug, Clone)]
+pub struct NearestIndex {
+    // The entries in tree order: the median of each range is its node, split on the channel
+    // `depth % 3`, with the lower half before it and the upper half after it
+    entries: Vec<(String, Color)>,
+}
+
+impl NearestIndex {
+    /// Builds the index from the colors of a map.
+    pub fn build(color_map: &HashMap<String, Color>) -> Self {
+        let mut entries: Vec<(String, Color)> = color_map.iter().map(|(name, color)| (name.clone(), *color)).collect();
+        Self::build_range(&mut entries, 0);
+        NearestIndex { entries }
+    }
+
+    fn channel(color: &Color, depth: usize) -> i32 {
+        match depth % 3 {
+            0 => color.r as i32,
+            1 => color.g as i32,
+            _ => color.b as i32,
+        }
+    }
+
+    fn build_range(entries: &mut [(String, Color)], depth: usize) {
+        if entries.len() <= 1 {
+            return;
+        }
+        entries.sort_by_key(|(_, color)| Self::channel(color, depth));
+        let mid = entries.len() / 2;
+        let (lower, upper) = entries.split_at_mut(mid);
+        Self::build_range(lower, depth + 1);
+        Self::build_range(&mut upper[1..], depth + 1);
+    }
+
+    /// Finds the indexed color closest to the given RGB value.
+    ///
+    /// # Returns:
+    /// * The name and RGB of the closest color, or `None` if the index is empty.
+    pub fn query(&self, r: u8, g: u8, b: u8) -> Option<(String, (u8, u8, u8))> {
+        let target = Color::new(r, g, b);
+        let mut best: Option<(i32, &(String, Color))> = None;
+        Self::search(&self.entries, &target, 0, &mut best);
+        best.map(|(_, (name, color))| (name.clone(), (color.r, color.g, color.b)))
+    }
+
+    fn search<'a>(entries: &'a [(String, Color)], target: &Color, depth: usize, best: &mut Option<(i32, &'a (String, Color))>) {
+        if entries.is_empty() {
+            return;
+        }
+        let mid = entries.len() / 2;
+        let node = &entries[mid];
+
+        let (dr, dg, db) = (node.1.r as i32 - target.r as i32, node.1.g as i32 - target.g as i32, node.1.b as i32 - target.b as i32);
+        let distance = dr * dr + dg * dg + db * db;
+        let better = match best {
+            Some((best_distance, best_entry)) => (distance, &node.0) < (*best_distance, &best_entry.0),
+            None => true,
+        };
+        if better {
+            *best = Some((distance, node));
+        }
+
+        // Visit the side of the target first, and the other one only if it can hold a closer (or tied) color
+        let offset = Self::channel(target, depth) - Self::channel(&node.1, depth);
+        let (near, far) = if offset < 0 { (&entries[..mid], &entries[mid + 1..]) } else { (&entries[mid + 1..], &entries[..mid]) };
+        Self::search(near, target, depth + 1, best);
+        let best_distance = best.map(|(distance, _)| distance).unwrap_or(i32::MAX);
+        if offset * offset <= best_distance {
+            Self::search(far, target, depth + 1, best);
+        }
+    }
+}
+
+/// Finds the color of the map with the highest WCAG contrast ratio against `against`.
+///
+/// Ties are broken by picking the alphabetically first name.
+///
+/// # Returns:
+/// * The name of the winning color and its contrast ratio, or `None` if the map is empty.
+pub fn most_contrasting(color_map: &HashMap<String, Color>, against: &Color) -> Option<(String, f32)> {
+    color_map
+        .iter()
+        .map(|(name, color)| (color.contrast_ratio(against), name))
+        .max_by(|a, b| a.0.total_cmp(&b.0).then_with(|| b.1.cmp(a.1)))
+        .map(|(ratio, name)| (name.clone(), ratio))
+}
+
+/// Names each RGB triple of `rgbs`: an exact match if the map has one, the nearest color otherwise.
+///
+/// The exact matches are indexed once for the whole batch. As everywhere else, ties (several
+/// names for the same RGB, or for the same distance) go to the alphabetically first name.
+///
+/// # Returns:
+/// * One entry per input, in the same order; every entry is `None` if the map is empty.
+pub fn names_for_rgbs(color_map: &HashMap<String, Color>, rgbs: &[(u8, u8, u8)], metric: DistanceMetric) -> Vec<Option<String>> {
+    let mut exact: HashMap<(u8, u8, u8), &String> = HashMap::new();
+    for (name, color) in color_map {
+        let entry = exact.entry((color.r, color.g, color.b)).or_insert(name);
+        if name < *entry {
+            *entry = name;
+        }
+    }
+
+    rgbs.iter()
+        .map(|&(r, g, b)| match exact.get(&(r, g, b)) {
+            Some(name) => Some((*name).clone()),
+            None => nearest_color_with(color_map, r, g, b, metric).map(|(name, _)| name),
+        })
+        .collect()
+}
+
+/// Arranges the colors in a grid for swatch displays: hue increases from the left column to the
+/// right one, and lightness decreases from the top row to the bottom one.
+///
+/// The colors sorted by hue are cut into `cols` columns whose lengths differ by at most one (the
+/// first `n % cols` columns get the extra color), each column is sorted from light to dark, and
+/// the grid is returned row by row. When the colors don't fill the grid, the bottom row has fewer
+/// than `cols` entries; with fewer colors than columns there is a single row.
+///
+/// # Returns:
+/// * The rows of the grid, or an empty vector if the map is empty or `cols` is `0`.
+pub fn arrange_grid(color_map: &HashMap<String, Color>, cols: usize) -> Vec<Vec<(String, Color)>> {
+    if cols == 0 || color_map.is_empty() {
+        return Vec::new();
+    }
+
+    let mut entries: Vec<(String, Color)> = color_map.iter().map(|(name, color)| (name.clone(), *color)).collect();
+    entries.sort_by(|a, b| a.1.to_hsl().0.total_cmp(&b.1.to_hsl().0).then_with(|| a.0.cmp(&b.0)));
+
+    let (base, extra) = (entries.len() / cols, entries.len() % cols);
+    let rows = base + usize::from(extra > 0);
+    let mut grid: Vec<Vec<(String, Color)>> = vec![Vec::new(); rows];
+    let mut rest = &mut entries[..];
+    for index in 0..cols {
+        let (column, tail) = rest.split_at_mut(base + usize::from(index < extra));
+        rest = tail;
+        column.sort_by(|a, b| b.1.to_hsl().2.total_cmp(&a.1.to_hsl().2).then_with(|| a.0.cmp(&b.0)));
+        for (row, entry) in column.iter().enumerate() {
+            grid[row].push(entry.clone());
+        }
+    }
+    grid
+}
+
+/// Writes the palette as an aligned table of name, hex code and RGB values, one color per line,
+/// sorted by name, e.g. for the output of a `colors list` command.
+///
+/// With the `ansi` feature, each row starts with a swatch of the color drawn with a 24-bit ANSI
+/// background escape code.
+///
+/// # Returns:
+/// * `Err(NamedColorsError::Io)` if writing fails.
+///
+/// ```rust
+/// use named_colors::colors::{print_palette_table, Color};
+/// use std::collections::HashMap;
+///
+/// let colors = HashMap::from([("red".to_string(), Color::new(255, 0, 0))]);
+/// let mut output = Vec::new();
+/// print_palette_table(&colors, &mut output).unwrap();
+/// assert!(String::from_utf8(output).unwrap().contains("#ff0000  rgb(255, 0, 0)"));
+/// ```
+pub fn print_palette_table<W: Write>(color_map: &HashMap<String, Color>, mut writer: W) -> Result<(), NamedColorsError> {
+    let mut names: Vec<&String> = color_map.keys().collect();
+    names.sort();
+    let width = names.iter().map(|name| name.chars().count()).max().unwrap_or(0);
+
+    for name in names {
+        let color = &color_map[name];
+        #[cfg(feature = "ansi")]
+        write!(writer, "\x1b[48;2;{};{};{}m    \x1b[0m  ", color.r, color.g, color.b)?;
+        writeln!(writer, "{:<width$}  {}  rgb({}, {}, {})", name, color.to_hex(), color.r, color.g, color.b, width = width)?;
+    }
+    Ok(())
+}
+
+/// Returns the position of the named color when all colors are sorted from darkest to lightest by
+/// [`Color::luminance`], e.g. to place it on a brightness scale. Equal luminances are ordered by name.
+///
+/// # Returns:
+/// * The 0-based rank, `0` being the darkest color.
+/// * `None` if no color has this name (looked up like [`get_color`]).
+pub fn color_rank(color_map: &HashMap<String, Color>, name: &str) -> Option<usize> {
+    // Resolved like `get_color`: the exact key, then the normalized name
+    let (name, color) = color_map.get_key_value(name).or_else(|| color_map.get_key_value(&normalize_name(name)))?;
+    let luminance = color.luminance();
+    let rank = color_map
+        .iter()
+        .filter(|(other_name, other)| {
+            let other_luminance = other.luminance();
+            other_luminance < luminance || (other_luminance == luminance && *other_name < name)
+        })
+        .count();
+    Some(rank)
+}
+
+/// Generates `n` easily distinguishable colors, e.g. for the categories of a chart.
+///
+/// Starting from red, each hue is the golden angle (about 137.5°) after the previous one, at a
+/// fixed saturation of 65% and lightness of 50%. The hues never repeat and every prefix of the
+/// sequence is well spread around the color wheel, so `generate_distinct_colors(n + 1)` only adds
+/// a color to `generate_distinct_colors(n)` and a chart can gain a category without recoloring
+/// the others.
+///
+/// ```rust
+/// use named_colors::colors::generate_distinct_colors;
+///
+/// let colors = generate_distinct_colors(3);
+/// assert_eq!(colors.len(), 3);
+/// assert!((colors[1].to_hsl().0 - 137.5).abs() < 1.0);
+/// assert_eq!(generate_distinct_colors(4)[..3], colors[..]);
+/// ```
+pub fn generate_distinct_colors(n: usize) -> Vec<Color> {
+    // 360° * (2 - φ), where φ is the golden ratio
+    const GOLDEN_ANGLE: f64 = 137.507_764_050_037_85;
+
+    (0..n).map(|i| Color::from_hsl((i as f64 * GOLDEN_ANGLE).rem_euclid(360.0) as f32, 0.65, 0.5)).collect()
+}
+
+/// Returns the shortest angle between the hues of two colors on the color wheel, from `0.0` to
+/// `180.0` degrees, e.g. to score how well colors harmonize.
+///
+/// # Returns:
+/// * `None` if either color is a gray (black and white included), since grays have no hue.
+pub fn hue_angle_between(a: &Color, b: &Color) -> Option<f32> {
+    let (h1, s1, _) = a.to_hsl();
+    let (h2, s2, _) = b.to_hsl();
+    if s1 == 0.0 || s2 == 0.0 {
+        return None;
+    }
+    let delta = (h1 - h2).abs() % 360.0;
+    Some(if delta > 180.0 { 360.0 - delta } else { delta })
+}
+
+/// Builds a readable `(background, foreground)` pair from a brand color: the background is the
+/// seed itself, made opaque, and the foreground is black or white (see [`Color::best_text_color`]).
+///
+/// When there's plenty of contrast to spare, the foreground is tinted with 15% of the seed for a
+/// less harsh look, as long as the pair still reaches 7:1 (WCAG AAA). Otherwise it stays pure
+/// black or white, which always reaches at least 4.5:1 (AA).
+pub fn theme_pair(seed: &Color) -> (Color, Color) {
+    let background = seed.with_alpha(255);
+    let plain = background.best_text_color();
+    let tinted = plain.blend(&background, 0.15);
+    let foreground = if tinted.contrast_ratio(&background) >= 7.0 { tinted } else { plain };
+    (background, foreground)
+}
+
+/// A change made to an [`EditablePalette`], with what's needed to revert it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PaletteEdit {
+    Add { name: String, color: Color },
+    Remove { name: String, color: Color },
+    Update { name: String, old: Color, new: Color },
+}
+
+impl PaletteEdit {
+    /// Returns the edit that reverts this one.
+    fn inverse(&self) -> PaletteEdit {
+        match self {
+            PaletteEdit::Add { name, color } => PaletteEdit::Remove { name: name.clone(), color: *color },
+            PaletteEdit::Remove { name, color } => PaletteEdit::Add { name: name.clone(), color: *color },
+            PaletteEdit::Update { name, old, new } => PaletteEdit::Update { name: name.clone(), old: *new, new: *old },
+        }
+    }
+
+    /// Makes the change on the colors.
+    fn apply(&self, colors: &mut HashMap<String, Color>) {
+        match self {
+            PaletteEdit::Add { name, color } | PaletteEdit::Update { name, new: color, .. } => {
+                colors.insert(name.clone(), *color);
+            }
+            PaletteEdit::Remove { name, .. } => {
+                colors.remove(name);
+            }
+        }
+    }
+}
+
+/// A color map with undo and redo, e.g. for the document of a palette editor.
+///
+/// Every successful [`add`](EditablePalette::add), [`remove`](EditablePalette::remove) or
+/// [`update`](EditablePalette::update) is recorded; making a new edit after undoing drops the
+/// edits that could have been redone.
+///
+/// ```rust
+/// use named_colors::colors::{Color, EditablePalette};
+///
+/// let mut palette = EditablePalette::new();
+/// palette.add("Brand Blue", Color::new(0, 82, 204)).unwrap();
+/// palette.update("brand_blue", Color::new(0, 90, 220)).unwrap();
+/// palette.undo();
+/// assert_eq!(palette.colors()["brand_blue"], Color::new(0, 82, 204));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EditablePalette {
+    colors: HashMap<String, Color>,
+    undo_stack: Vec<PaletteEdit>,
+    redo_stack: Vec<PaletteEdit>,
+}
+
+impl EditablePalette {
+    /// Creates an empty palette.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the current colors.
+    pub fn colors(&self) -> &HashMap<String, Color> {
+        &self.colors
+    }
+
+    /// Consumes the palette and returns its colors, dropping the history.
+    pub fn into_inner(self) -> HashMap<String, Color> {
+        self.colors
+    }
+
+    /// Returns the key of an existing color: the exact name, or else its normalized form.
+    fn existing_key(&self, name: &str) -> Result<String, NamedColorsError> {
+        if self.colors.contains_key(name) {
+            return Ok(name.to_string());
+        }
+        let normalized = normalize_name(name);
+        if self.colors.contains_key(&normalized) {
+            Ok(normalized)
+        } else {
+            Err(NamedColorsError::ColorNotFound(name.to_string()))
+        }
+    }
+
+    /// Applies a new edit and adds it to the history.
+    fn record(&mut self, edit: PaletteEdit) {
+        edit.apply(&mut self.colors);
+        self.undo_stack.push(edit);
+        self.redo_stack.clear();
+    }
+
+    /// Adds a color under its normalized name (see [`normalize_name`]).
+    ///
+    /// # Returns:
+    /// * `Err(NamedColorsError::DuplicateColor)` if the palette already has this name.
+    /// * `Err(NamedColorsError::InvalidName)` if the name is empty once normalized.
+    pub fn add(&mut self, name: &str, color: Color) -> Result<(), NamedColorsError> {
+        let normalized = normalize_name(name);
+        if normalized.is_empty() {
+            return Err(NamedColorsError::InvalidName(name.to_string()));
+        }
+        if self.colors.contains_key(&normalized) {
+            return Err(NamedColorsError::DuplicateColor(normalized));
+        }
+        self.record(PaletteEdit::Add { name: normalized, color });
+        Ok(())
+    }
+
+    /// Removes a color.
+    ///
+    /// # Returns:
+    /// * The removed color, or `Err(NamedColorsError::ColorNotFound)`.
+    pub fn remove(&mut self, name: &str) -> Result<Color, NamedColorsError> {
+        let name = self.existing_key(name)?;
+        let color = self.colors[&name];
+        self.record(PaletteEdit::Remove { name, color });
+        Ok(color)
+    }
+
+    /// Replaces the value of an existing color.
+    ///
+    /// # Returns:
+    /// * The previous color, or `Err(NamedColorsError::ColorNotFound)`.
+    pub fn update(&mut self, name: &str, color: Color) -> Result<Color, NamedColorsError> {
+        let name = self.existing_key(name)?;
+        let old = self.colors[&name];
+        self.record(PaletteEdit::Update { name, old, new: color });
+        Ok(old)
+    }
+
+    /// Reverts the last edit.
+    ///
+    /// # Returns:
+    /// * `false` if there was nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let Some(edit) = self.undo_stack.pop() else {
+            return false;
+        };
+        edit.inverse().apply(&mut self.colors);
+        self.redo_stack.push(edit);
+        true
+    }
+
+    /// Makes the last undone edit again.
+    ///
+    /// # Returns:
+    /// * `false` if there was nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        let Some(edit) = self.redo_stack.pop() else {
+            return false;
+        };
+        edit.apply(&mut self.colors);
+        self.undo_stack.push(edit);
+        true
+    }
+}
+
+impl From<HashMap<String, Color>> for EditablePalette {
+    /// Starts editing an existing map, with an empty history.
+    fn from(colors: HashMap<String, Color>) -> Self {
+        EditablePalette { colors, ..Self::default() }
+    }
+}
+
+/// Builds a gradient of `steps` colors from `from` to `to`, both included, interpolated in
+/// OKLab (see [`Color::to_oklab`]).
+///
+/// Interpolating in a perceptually uniform space gives steps that look evenly spaced, and
+/// midpoints that don't turn as dark and muddy as with an sRGB blend. The alpha is interpolated
+/// linearly.
+///
+/// # Returns:
+/// * `steps` colors; a single step gives just `from`.
+pub fn gradient_oklab(from: &Color, to: &Color, steps: usize) -> Vec<Color> {
+    let (l1, a1, b1) = from.to_oklab();
+    let (l2, a2, b2) = to.to_oklab();
+    (0..steps)
+        .map(|i| {
+            let t = if steps == 1 { 0.0 } else { i as f32 / (steps - 1) as f32 };
+            let lerp = |x: f32, y: f32| x + (y - x) * t;
+            let alpha = lerp(from.a as f32, to.a as f32).round() as u8;
+            Color::from_oklab(lerp(l1, l2), lerp(a1, a2), lerp(b1, b2)).with_alpha(alpha)
+        })
+        .collect()
+}
+
+/// A named color map registered on a [`ResolverConfig`].
+#[derive(Debug, Clone)]
+struct ResolverLayer {
+    name: String,
+    priority: i32,
+    colors: HashMap<String, Color>,
+}
+
+/// Layered color maps, e.g. user overrides over a workspace theme over the builtin colors, looked
+/// up by priority with [`resolve_name`] instead of being merged into one map.
+///
+/// Higher priorities are checked first; layers with the same priority in the order they were added.
+///
+/// ```rust
+/// use named_colors::colors::{builtin_colors, resolve_name, Color, ResolverConfig};
+/// use std::collections::HashMap;
+///
+/// let config = ResolverConfig::new()
+///     .with_layer("builtin", 0, builtin_colors().unwrap())
+///     .with_layer("user", 10, HashMap::from([("red".to_string(), Color::new(200, 0, 0))]));
+/// assert_eq!(resolve_name(&config, "red"), Some((200, 0, 0)));
+/// assert_eq!(config.layer_of("navy"), Some("builtin"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ResolverConfig {
+    // Sorted by descending priority
+    layers: Vec<ResolverLayer>,
+}
+
+impl ResolverConfig {
+    /// Creates a configuration without any layer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a layer of colors under `name` with the given priority.
+    pub fn with_layer(mut self, name: &str, priority: i32, colors: HashMap<String, Color>) -> Self {
+        let index = self.layers.partition_point(|layer| layer.priority >= priority);
+        self.layers.insert(index, ResolverLayer { name: name.to_string(), priority, colors });
+        self
+    }
+
+    /// Returns the name of the layer a color resolves from, if any layer has it.
+    pub fn layer_of(&self, color_name: &str) -> Option<&str> {
+        self.lookup(color_name).map(|(layer, _)| layer.name.as_str())
+    }
+
+    /// Finds the color in the first layer that has it, with that layer.
+    fn lookup(&self, color_name: &str) -> Option<(&ResolverLayer, &Color)> {
+        self.layers.iter().find_map(|layer| get_color(&layer.colors, color_name).map(|color| (layer, color)))
+    }
+}
+
+/// Looks up a color in the layers of `config`, highest priority first (see [`get_color`]).
+///
+/// # Returns:
+/// * The RGB of the color in the first layer that has it, or `None`.
+pub fn resolve_name(config: &ResolverConfig, name: &str) -> Option<(u8, u8, u8)> {
+    config.lookup(name).map(|(_, color)| (color.r, color.g, color.b))
+}
+
+/// Converts every color of the map to another color space, e.g. to cluster or plot a palette.
+///
+/// ```rust
+/// use named_colors::colors::{convert_palette, Color, ColorSpace, ConvertedColor};
+/// use std::collections::HashMap;
+///
+/// let colors = HashMap::from([("red".to_string(), Color::new(255, 0, 0))]);
+/// assert_eq!(convert_palette(&colors, ColorSpace::Hsl)["red"], ConvertedColor::Three(0.0, 1.0, 0.5));
+/// ```
+///
+/// # Returns:
+/// * The components of each color, as returned by the conversion named in [`ColorSpace`]:
+///   [`ConvertedColor::Four`] for [`ColorSpace::Cmyk`], [`ConvertedColor::Three`] for the others.
+pub fn convert_palette(color_map: &HashMap<String, Color>, space: ColorSpace) -> HashMap<String, ConvertedColor> {
+    color_map
+        .iter()
+        .map(|(name, color)| {
+            let converted = match space {
+                ColorSpace::Hsl => color.to_hsl().into(),
+                ColorSpace::Hsv => color.to_hsv().into(),
+                ColorSpace::Lab => color.to_lab().into(),
+                ColorSpace::Oklab => color.to_oklab().into(),
+                ColorSpace::Cmyk => color.to_cmyk().into(),
+            };
+            (name.clone(), converted)
+        })
+        .collect()
+}
+
+/// Finds the colors of the map that complete a harmony with `base`: those whose hue is within
+/// `tolerance` degrees of one of the other hues of `scheme` (see [`HarmonyScheme::angles`]).
+///
+/// Grays have no hue, so they never match, and a gray `base` matches nothing.
+///
+/// ```rust
+/// use named_colors::colors::{builtin_colors, harmonious_with, Color, HarmonyScheme};
+///
+/// let colors = builtin_colors().unwrap();
+/// let complements = harmonious_with(&colors, &Color::new(255, 0, 0), HarmonyScheme::Complementary, 5.0);
+/// assert!(complements.contains(&"cyan".to_string()));
+/// ```
+///
+/// # Returns:
+/// * The matching names, sorted alphabetically.
+pub fn harmonious_with(color_map: &HashMap<String, Color>, base: &Color, scheme: HarmonyScheme, tolerance: f32) -> Vec<String> {
+    let (base_hue, base_saturation, _) = base.to_hsl();
+    if base_saturation == 0.0 {
+        return Vec::new();
+    }
+    let targets: Vec<f32> = scheme.angles().iter().filter(|&&angle| angle != 0.0).map(|angle| base_hue + angle).collect();
+
+    let mut names: Vec<String> = color_map
+        .iter()
+        .filter(|(_, color)| {
+            let (hue, saturation, _) = color.to_hsl();
+            saturation > 0.0
+                && targets.iter().any(|target| {
+                    let delta = (hue - target).rem_euclid(360.0);
+                    delta.min(360.0 - delta) <= tolerance
+                })
+        })
+        .map(|(name, _)| name.clone())
+        .collect();
+    names.sort();
+    names
+}
+
+/// Unit tests for the `Color` type and the color loading functions.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_JSON: &str = r#"{"red": {"r": 255, "g": 0, "b": 0}, "navy": {"r": 0, "g": 0, "b": 128}}"#;
+
+    /// Writes `content` to a file in the temporary directory and returns its path.
+    fn write_temp_file(name: &str, content: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("named_colors_{}_{}", std::process::id(), name));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    /// Tests loading colors from a JSON file on disk.
+    #[test]
+    fn test_load_colors_from_path() {
+        let path = write_temp_file("sync.json", SAMPLE_JSON);
+        let colors = load_colors_from_path(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(colors.len(), 2);
+        assert_eq!(colors["navy"], Color::new(0, 0, 128));
+        assert!(matches!(load_colors_from_path(&path), Err(NamedColorsError::Io(_))));
+    }
+
+    /// Tests loading colors from a JSON file on disk asynchronously.
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_load_colors_from_path_async() {
+        let path = write_temp_file("async.json", SAMPLE_JSON);
+        let colors = load_colors_from_path_async(&path).await.unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(colors["red"], Color::new(255, 0, 0));
+        assert_eq!(colors["navy"], Color::new(0, 0, 128));
+    }
+
+    /// Tests that the compressed embedded colors decompress to the same map as the plain JSON file.
+    #[cfg(feature = "compressed-assets")]
+    #[test]
+    fn test_compressed_assets_match_json() {
+        let decompressed: HashMap<String, Value> = serde_json::from_str(embedded_colors_json().unwrap()).unwrap();
+        let plain: HashMap<String, Value> = serde_json::from_str(include_str!("../named_colors.json")).unwrap();
+        assert_eq!(decompressed, plain);
+    }
+
+    /// Tests that fully desaturating red gives a gray equal to its HSL grayscale.
+    #[test]
+    fn test_desaturate_red_gives_gray() {
+        let red = Color::new(255, 0, 0);
+        let gray = red.desaturate(1.0);
+        assert_eq!(gray.r, gray.g);
+        assert_eq!(gray.g, gray.b);
+
+        let (h, _, l) = red.to_hsl();
+        assert_eq!(gray, Color::from_hsl(h, 0.0, l));
+    }
+
+    /// Tests that saturating a gray leaves it unchanged.
+    #[test]
+    fn test_saturate_gray_does_nothing() {
+        let gray = Color::new(128, 128, 128);
+        assert_eq!(gray.saturate(0.5), gray);
+    }
+
+    /// Tests that saturation changes are clamped.
+    #[test]
+    fn test_saturate_clamps() {
+        let muted = Color::from_hsl(200.0, 0.5, 0.5);
+        assert!((muted.saturate(2.0).to_hsl().1 - 1.0).abs() < 0.01);
+        assert!((muted.desaturate(0.25).to_hsl().1 - 0.25).abs() < 0.01);
+        assert_eq!(muted.desaturate(2.0).to_hsl().1, 0.0);
+    }
+
+    /// Tests that rotating red by 120° and 240° lands on green and blue.
+    #[test]
+    fn test_rotate_hue() {
+        let red = Color::new(255, 0, 0);
+        assert_eq!(red.rotate_hue(120.0), Color::new(0, 255, 0));
+        assert_eq!(red.rotate_hue(240.0), Color::new(0, 0, 255));
+        assert_eq!(red.rotate_hue(-120.0), Color::new(0, 0, 255));  // Negative angles wrap around
+    }
+
+    /// Tests that rotating the hue of a gray does nothing.
+    #[test]
+    fn test_rotate_hue_achromatic() {
+        let gray = Color::new(128, 128, 128);
+        assert_eq!(gray.rotate_hue(90.0), gray);
+    }
+
+    /// Tests the number of colors returned by each harmony scheme.
+    #[test]
+    fn test_harmony_counts() {
+        let base = Color::new(200, 60, 40);
+        assert_eq!(base.harmony(HarmonyScheme::Complementary).len(), 2);
+        assert_eq!(base.harmony(HarmonyScheme::Analogous).len(), 3);
+        assert_eq!(base.harmony(HarmonyScheme::Triadic).len(), 3);
+        assert_eq!(base.harmony(HarmonyScheme::Tetradic).len(), 4);
+        assert_eq!(base.harmony(HarmonyScheme::SplitComplementary).len(), 3);
+        assert_eq!(base.harmony(HarmonyScheme::Analogous)[0], base);  // The base comes first
+    }
+
+    /// Tests that a triadic palette has three hues roughly 120° apart.
+    #[test]
+    fn test_harmony_triadic_hues() {
+        let base = Color::new(200, 60, 40);
+        let hues: Vec<f32> = base
+            .harmony(HarmonyScheme::Triadic)
+            .iter()
+            .map(|color| color.to_hsl().0)
+            .collect();
+
+        for (i, expected) in [0.0, 120.0, 240.0].iter().enumerate() {
+            let delta = (hues[i] - hues[0]).rem_euclid(360.0);
+            assert!((delta - expected).abs() < 2.0, "hue {} is {}° from the base", i, delta);
+        }
+    }
+
+    /// Tests that all names sharing the same RGB are returned in alphabetical order.
+    #[test]
+    fn test_get_all_names_by_rgb() {
+        let mut colors = HashMap::new();
+        colors.insert("grey".to_string(), Color::new(128, 128, 128));
+        colors.insert("gray".to_string(), Color::new(128, 128, 128));
+        colors.insert("red".to_string(), Color::new(255, 0, 0));
+
+        assert_eq!(get_all_names_by_rgb(&colors, 128, 128, 128), vec!["gray", "grey"]);
+        assert_eq!(get_all_names_by_rgb(&colors, 255, 0, 0), vec!["red"]);
+        assert!(get_all_names_by_rgb(&colors, 1, 2, 3).is_empty());
+    }
+
+    /// Tests the canonical form produced by `normalize_name`.
+    #[test]
+    fn test_normalize_name() {
+        assert_eq!(normalize_name("  Sky Blue "), "sky_blue");
+        assert_eq!(normalize_name("sky-blue"), "sky_blue");
+        assert_eq!(normalize_name("Sky -  _Blue"), "sky_blue");
+        assert_eq!(normalize_name("red"), "red");
+    }
+
+    /// Tests that name variants collide when added to the map.
+    #[test]
+    fn test_add_color_normalizes_names() {
+        let mut colors = HashMap::new();
+        assert!(add_color(&mut colors, "Sky Blue", 135, 206, 235).is_ok());
+        assert!(matches!(add_color(&mut colors, "sky-blue", 0, 0, 0), Err(NamedColorsError::DuplicateColor(_))));
+        assert!(matches!(add_color(&mut colors, "sky_blue", 0, 0, 0), Err(NamedColorsError::DuplicateColor(_))));
+        assert!(matches!(add_color(&mut colors, " - ", 0, 0, 0), Err(NamedColorsError::InvalidName(_))));
+
+        assert_eq!(colors.len(), 1);
+        assert_eq!(colors["sky_blue"], Color::new(135, 206, 235));
+    }
+
+    /// Tests consuming the streaming loader over an in-memory JSON palette.
+    #[test]
+    fn test_load_colors_streaming() {
+        let entries: Vec<(String, Color)> = load_colors_streaming(std::io::Cursor::new(SAMPLE_JSON))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(entries, vec![
+            ("red".to_string(), Color::new(255, 0, 0)),
+            ("navy".to_string(), Color::new(0, 0, 128)),
+        ]);
+
+        assert_eq!(load_colors_streaming(std::io::Cursor::new(" { } ")).count(), 0);
+    }
+
+    /// Tests that the streaming loader decodes escaped and non-ASCII keys like serde_json does.
+    #[test]
+    fn test_load_colors_streaming_escaped_keys() {
+        let json = r#"{"sky \"blue\"": {"r": 0, "g": 0, "b": 255}, "gr\u00fcn": {"r": 0, "g": 128, "b": 0}, "紅": {"r": 255, "g": 0, "b": 0}, "a\\b,c:{}": {"r": 1, "g": 2, "b": 3}}"#;
+        let entries: Vec<(String, Color)> = load_colors_streaming(std::io::Cursor::new(json))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(entries, vec![
+            ("sky \"blue\"".to_string(), Color::new(0, 0, 255)),
+            ("grün".to_string(), Color::new(0, 128, 0)),
+            ("紅".to_string(), Color::new(255, 0, 0)),
+            ("a\\b,c:{}".to_string(), Color::new(1, 2, 3)),
+        ]);
+    }
+
+    /// Tests that trailing data after the palette is reported as an error.
+    #[test]
+    fn test_load_colors_streaming_trailing_data() {
+        let entries: Vec<_> = load_colors_streaming(std::io::Cursor::new(r#"{"red": {"r": 255, "g": 0, "b": 0}} {"#)).collect();
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].is_ok());
+        assert!(matches!(entries[1], Err(NamedColorsError::ParseError(_))));
+    }
+
+    /// Tests that the streaming loader yields the valid entries, then stops at the first error.
+    #[test]
+    fn test_load_colors_streaming_error() {
+        let json = r#"{"red": {"r": 255, "g": 0, "b": 0}, "bad": {"r": 300}, "blue": {"r": 0, "g": 0, "b": 255}}"#;
+        let entries: Vec<_> = load_colors_streaming(std::io::Cursor::new(json)).collect();
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].is_ok());
+        assert!(matches!(entries[1], Err(NamedColorsError::ParseError(_))));
+    }
+
+    /// Tests that the strict loader rejects duplicate keys while the lenient one keeps the last value.
+    #[test]
+    fn test_load_colors_from_file_strict_duplicates() {
+        let json = r#"{"red": {"r": 255, "g": 0, "b": 0}, "red": {"r": 200, "g": 0, "b": 0}, "blue": {"r": 0, "g": 0, "b": 255}}"#;
+
+        let lenient = load_colors_from_file(json).unwrap();
+        assert_eq!(lenient["red"], Color::new(200, 0, 0));
+
+        match load_colors_from_file_strict(json) {
+            Err(NamedColorsError::DuplicateKeys(names)) => assert_eq!(names, vec!["red"]),
+            other => panic!("expected duplicate keys error, got {:?}", other),
+        }
+        assert_eq!(load_colors_from_file_strict(SAMPLE_JSON).unwrap().len(), 2);
+    }
+
+    /// Tests the linear conversions round trip.
+    #[test]
+    fn test_linear_round_trip() {
+        for value in [0, 1, 10, 100, 128, 200, 255] {
+            let color = Color::new(value, value, value);
+            let (r, g, b) = color.to_linear();
+            assert_eq!(Color::from_linear(r, g, b), color);
+        }
+    }
+
+    /// Tests averaging red and blue in linear space.
+    #[test]
+    fn test_average_color() {
+        let mut colors = HashMap::new();
+        colors.insert("red".to_string(), Color::new(255, 0, 0));
+        colors.insert("blue".to_string(), Color::new(0, 0, 255));
+
+        // Half of full intensity in linear space is 188 once gamma encoded, not 128
+        assert_eq!(average_color(&colors, &["red", "blue"]).unwrap(), Color::new(188, 0, 188));
+        assert_eq!(average_color(&colors, &["Red"]).unwrap(), Color::new(255, 0, 0));
+
+        match average_color(&colors, &["red", "mauve", "teal"]) {
+            Err(NamedColorsError::ColorNotFound(name)) => assert_eq!(name, "mauve"),
+            other => panic!("expected a missing color error, got {:?}", other),
+        }
+        assert!(matches!(average_color(&colors, &[]), Err(NamedColorsError::NoColors)));
+    }
+
+    /// Tests the conversions between 8-bit and 16-bit colors.
+    #[test]
+    fn test_color16_conversions() {
+        let white = Color16::from(Color::new(255, 255, 255));
+        assert_eq!(white, Color16 { r: 65535, g: 65535, b: 65535 });
+
+        // Every 8-bit value survives a round trip through 16 bits
+        for value in 0..=255u8 {
+            let color = Color::new(value, value, value);
+            assert_eq!(Color::from(Color16::from(color)), color);
+        }
+        assert_eq!(Color::from(Color16 { r: 32896, g: 0, b: 65535 }), Color::new(128, 0, 255));
+    }
+
+    /// Tests the CIELAB conversion of a few reference colors.
+    #[test]
+    fn test_to_lab() {
+        let (l, a, b) = Color::new(255, 255, 255).to_lab();
+        assert!((l - 100.0).abs() < 0.1 && a.abs() < 0.1 && b.abs() < 0.1);
+
+        let (l, a, b) = Color::new(255, 0, 0).to_lab();
+        assert!((l - 53.24).abs() < 0.1 && (a - 80.09).abs() < 0.2 && (b - 67.20).abs() < 0.2);
+    }
+
+    /// Tests that each metric can pick a different nearest color for a tricky input.
+    #[test]
+    fn test_nearest_color_with_metrics() {
+        let mut colors = HashMap::new();
+        colors.insert("mediumturquoise".to_string(), Color::new(72, 209, 204));
+        colors.insert("cornflowerblue".to_string(), Color::new(100, 149, 237));
+        colors.insert("deepskyblue".to_string(), Color::new(0, 191, 255));
+
+        let nearest = |metric| nearest_color_with(&colors, 61, 170, 216, metric).unwrap().0;
+        assert_eq!(nearest(DistanceMetric::Euclidean), "mediumturquoise");
+        assert_eq!(nearest(DistanceMetric::WeightedEuclidean), "cornflowerblue");
+        assert_eq!(nearest(DistanceMetric::CIE76), "deepskyblue");
+        assert_eq!(nearest(DistanceMetric::CIE94), "deepskyblue");
+
+        // An exact match wins with any metric
+        assert_eq!(nearest_color_with(&colors, 0, 191, 255, DistanceMetric::Euclidean), Some(("deepskyblue".to_string(), (0, 191, 255))));
+        assert_eq!(nearest_color_with(&HashMap::new(), 0, 0, 0, DistanceMetric::CIE76), None);
+    }
+
+    /// Tests that the ordered loader iterates in the JSON declaration order.
+    #[cfg(feature = "indexmap")]
+    #[test]
+    fn test_load_colors_ordered() {
+        let json = r#"{"white": {"r": 255, "g": 255, "b": 255}, "black": {"r": 0, "g": 0, "b": 0}, "navy": {"r": 0, "g": 0, "b": 128}}"#;
+        let colors = load_colors_ordered(json).unwrap();
+        let names: Vec<&str> = colors.keys().map(|name| name.as_str()).collect();
+        assert_eq!(names, vec!["white", "black", "navy"]);
+    }
+
+    /// Tests the float conversions, including clamping of out of range values.
+    #[test]
+    fn test_from_f32_clamps() {
+        assert_eq!(Color::from_f32(1.5, -0.2, 0.5), Color::new(255, 0, 128));
+        assert_eq!(Color::from_f32(2.0, 1.0, -1.0), Color::new(255, 255, 0));
+        assert_eq!(Color::new(255, 0, 51).to_f32(), (1.0, 0.0, 0.2));
+    }
+
+    /// Tests that the sRGB and linear midpoints of black and white differ.
+    #[test]
+    fn test_blend_in_spaces() {
+        let black = Color::new(0, 0, 0);
+        let white = Color::new(255, 255, 255);
+
+        assert_eq!(black.blend(&white, 0.5), Color::new(128, 128, 128));
+        assert_eq!(black.blend_in(&white, 0.5, BlendSpace::Linear), Color::new(188, 188, 188));
+        assert_eq!(black.blend_in(&white, 0.0, BlendSpace::Linear), black);
+        assert_eq!(black.blend_in(&white, 3.0, BlendSpace::Srgb), white);  // `t` is clamped
+    }
+
+    /// Tests that HSL blending goes along the shorter hue arc.
+    #[test]
+    fn test_blend_in_hsl_shorter_arc() {
+        let red = Color::from_hsl(350.0, 1.0, 0.5);
+        let orange = Color::from_hsl(30.0, 1.0, 0.5);
+        let (h, _, _) = red.blend_in(&orange, 0.5, BlendSpace::Hsl).to_hsl();
+        assert!((h - 10.0).abs() < 1.0, "hue is {}", h);  // Not 190°, the long way around
+    }
+
+    /// Tests web-safe detection and snapping.
+    #[test]
+    fn test_web_safe() {
+        let safe = Color::new(51, 204, 255);
+        assert!(safe.is_web_safe());
+        assert_eq!(safe.to_web_safe(), safe);
+
+        let unsafe_color = Color::new(30, 130, 250);
+        assert!(!unsafe_color.is_web_safe());
+        assert_eq!(unsafe_color.to_web_safe(), Color::new(51, 153, 255));
+        assert!(unsafe_color.to_web_safe().is_web_safe());
+    }
+
+    /// Tests the `color!` macro in its hex and tuple forms.
+    #[test]
+    fn test_color_macro() {
+        const RED: Color = crate::color!(#ff0000);
+        assert_eq!(RED, Color::new(255, 0, 0));
+        assert_eq!(crate::color!(#00ff00), Color::new(0, 255, 0));
+        assert_eq!(crate::color!(#F80), Color::new(255, 136, 0));
+        assert_eq!(crate::color!("#0000ff"), Color::new(0, 0, 255));
+        assert_eq!(crate::color!(128, 0, 128), Color::new(128, 0, 128));
+    }
+
+    /// Tests the const hex parser on invalid input.
+    #[test]
+    fn test_parse_hex_const_invalid() {
+        assert_eq!(parse_hex_const("#ff00zz"), None);
+        assert_eq!(parse_hex_const("#ff000"), None);
+        assert_eq!(parse_hex_const("#ff0000000"), None);
+        assert_eq!(parse_hex_const(""), None);
+    }
+
+    /// Tests the percentage channel accessors and setters.
+    #[test]
+    fn test_channel_percentages() {
+        let color = Color::new(255, 128, 0);
+        assert_eq!(color.red_pct(), 1.0);
+        assert!((color.green_pct() - 0.502).abs() < 0.001);
+        assert_eq!(color.blue_pct(), 0.0);
+
+        let updated = color.with_red_pct(0.0).with_green_pct(1.5).with_blue_pct(0.5);
+        assert_eq!(updated, Color::new(0, 255, 128));
+    }
+
+    /// Tests that a monochromatic palette keeps the base hue and contains the base color.
+    #[test]
+    fn test_monochromatic() {
+        let base = Color::new(70, 130, 180);
+        let (base_hue, _, _) = base.to_hsl();
+        let palette = base.monochromatic(5);
+
+        assert_eq!(palette.len(), 5);
+        assert!(palette.contains(&base));
+        for color in &palette {
+            assert!((color.to_hsl().0 - base_hue).abs() < 3.0, "{:?} drifted from the base hue", color);
+        }
+        for pair in palette.windows(2) {
+            assert!(pair[0].to_hsl().2 < pair[1].to_hsl().2);  // Sorted from dark to light
+        }
+        assert!(base.monochromatic(0).is_empty());
+    }
+
+    /// Tests formatting and parsing hex codes.
+    #[test]
+    fn test_hex_round_trip() {
+        assert_eq!(Color::new(255, 99, 71).to_hex(), "#ff6347");
+        assert_eq!(Color::from_hex("#FF6347").unwrap(), Color::new(255, 99, 71));
+        assert_eq!(Color::from_hex("f00").unwrap(), Color::new(255, 0, 0));
+        assert!(matches!(Color::from_hex("#ff634"), Err(NamedColorsError::InvalidHex(_))));
+    }
+
+    /// Tests serializing fields and maps through `hex_serde`.
+    #[test]
+    fn test_hex_serde() {
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Theme {
+            #[serde(with = "hex_serde")]
+            accent: Color,
+            #[serde(with = "hex_serde::map")]
+            palette: HashMap<String, Color>,
+        }
+
+        let mut palette = HashMap::new();
+        palette.insert("navy".to_string(), Color::new(0, 0, 128));
+        let theme = Theme { accent: Color::new(255, 0, 0), palette };
+
+        let json = serde_json::to_string(&theme).unwrap();
+        assert_eq!(json, r##"{"accent":"#ff0000","palette":{"navy":"#000080"}}"##);
+        assert_eq!(serde_json::from_str::<Theme>(&json).unwrap(), theme);
+        assert!(serde_json::from_str::<Theme>(r##"{"accent":"red","palette":{}}"##).is_err());
+    }
+
+    /// Tests that batch lookups keep the input order and report missing names.
+    #[test]
+    fn test_get_colors_by_names() {
+        let colors = load_colors_from_file(SAMPLE_JSON).unwrap();
+        let results = get_colors_by_names(&colors, &["navy", "invalid_color", "RED"]);
+        assert_eq!(results, vec![Some((0, 0, 128)), None, Some((255, 0, 0))]);
+    }
+
+    /// Tests inverting colors.
+    #[test]
+    fn test_invert() {
+        assert_eq!(Color::new(255, 255, 255).invert(), Color::new(0, 0, 0));
+        assert_eq!(Color::new(255, 0, 0).invert(), Color::new(0, 255, 255));
+        assert_eq!(Color::new(12, 34, 56).invert().invert(), Color::new(12, 34, 56));
+    }
+
+    /// Tests the line-based loader with comments, blank lines and a malformed line.
+    #[test]
+    fn test_load_colors_from_ini() {
+        let data = "# Theme colors\n\n; accents\nSky Blue = #87ceeb\nnavy = 0, 0, 128\n";
+        let colors = load_colors_from_ini(data).unwrap();
+        assert_eq!(colors.len(), 2);
+        assert_eq!(colors["sky_blue"], Color::new(135, 206, 235));
+        assert_eq!(colors["navy"], Color::new(0, 0, 128));
+
+        let malformed = "red = #ff0000\n\ngreen = 0, 300, 0\n";
+        match load_colors_from_ini(malformed) {
+            Err(NamedColorsError::InvalidLine { line, .. }) => assert_eq!(line, 3),
+            other => panic!("expected an invalid line error, got {:?}", other),
+        }
+        assert!(matches!(load_colors_from_ini("blue #0000ff"), Err(NamedColorsError::InvalidLine { line: 1, .. })));
+    }
+
+    /// Tests the Levenshtein distance.
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("green", "green"), 0);
+        assert_eq!(levenshtein("gren", "green"), 1);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "red"), 3);
+    }
+
+    /// Tests that a failed lookup suggests the closest color names.
+    #[test]
+    fn test_get_color_by_name_or_suggest() {
+        let mut colors = load_colors_from_file(SAMPLE_JSON).unwrap();
+        colors.insert("green".to_string(), Color::new(0, 255, 0));
+
+        assert_eq!(get_color_by_name_or_suggest(&colors, "Green").unwrap(), (0, 255, 0));
+
+        let err = get_color_by_name_or_suggest(&colors, "gren").unwrap_err();
+        assert!(err.to_string().contains("did you mean 'green'?"), "{}", err);
+
+        let err = get_color_by_name_or_suggest(&colors, "chartreuse").unwrap_err();
+        assert_eq!(err.to_string(), "color 'chartreuse' not found");
+    }
+
+    /// Tests that the builtin JSON is valid and contains the expected colors.
+    #[test]
+    fn test_builtin_json_parses() {
+        let colors = load_colors_from_file(builtin_json()).unwrap();
+        assert_eq!(colors["red"], Color::new(255, 0, 0));
+        assert!(serde_json::from_str::<Value>(builtin_json()).unwrap().is_object());
+    }
+
+    /// Tests that prefixed colors are retrievable and duplicates reject the whole batch.
+    #[test]
+    fn test_add_colors_with_prefix() {
+        let mut colors = HashMap::new();
+        add_colors_with_prefix(&mut colors, "brand", &[("blue", 0, 82, 204), ("Light Blue", 76, 154, 255)]).unwrap();
+        add_colors_with_prefix(&mut colors, "accent", &[("blue", 0, 184, 217)]).unwrap();
+
+        assert_eq!(get_colors_by_names(&colors, &["brand_blue", "brand_light_blue", "accent_blue"]), vec![
+            Some((0, 82, 204)),
+            Some((76, 154, 255)),
+            Some((0, 184, 217)),
+        ]);
+
+        let result = add_colors_with_prefix(&mut colors, "brand", &[("green", 0, 135, 90), ("blue", 0, 0, 0)]);
+        assert!(matches!(result, Err(NamedColorsError::DuplicateColor(name)) if name == "brand_blue"));
+        assert!(!colors.contains_key("brand_green"));
+    }
+
+    /// Tests luminance, contrast ratio and luminance difference between black and white.
+    #[test]
+    fn test_luminance_difference() {
+        let black = Color::new(0, 0, 0);
+        let white = Color::new(255, 255, 255);
+
+        assert!((black.luminance_difference(&white) - 1.0).abs() < 0.001);
+        assert_eq!(white.luminance_difference(&white), 0.0);
+        assert!((black.contrast_ratio(&white) - 21.0).abs() < 0.01);
+        assert!((Color::new(255, 0, 0).luminance() - 0.2126).abs() < 0.001);
+    }
+
+    /// Tests reading and writing every channel generically.
+    #[test]
+    fn test_channel_get_set() {
+        let mut color = Color::new(10, 20, 30);
+        for channel in Channel::ALL {
+            let value = color.get(channel);
+            color.set(channel, value * 2);
+        }
+        assert_eq!(color, Color::new(20, 40, 60));
+        assert_eq!(color.get(Channel::Green), 40);
+    }
+
+    /// Tests finding color names in a sentence without partial-word matches.
+    #[test]
+    fn test_extract_colors_from_text() {
+        let mut colors = load_colors_from_file(SAMPLE_JSON).unwrap();
+        colors.insert("blue".to_string(), Color::new(0, 0, 255));
+        colors.insert("green".to_string(), Color::new(0, 255, 0));
+        colors.insert("sky_blue".to_string(), Color::new(135, 206, 235));
+
+        let found = extract_colors_from_text(&colors, "Paint it Blue, then redo the GREEN wall.");
+        assert_eq!(found, vec![
+            ("blue".to_string(), (0, 0, 255)),
+            ("green".to_string(), (0, 255, 0)),
+        ]);
+
+        let found = extract_colors_from_text(&colors, "A sky-blue sky");
+        assert_eq!(found, vec![("sky_blue".to_string(), (135, 206, 235))]);
+        assert!(extract_colors_from_text(&colors, "reddish tones").is_empty());
+    }
+
+    /// Tests that the alpha channel defaults to opaque and is only serialized when translucent.
+    #[test]
+    fn test_alpha_serialization() {
+        let red: Color = serde_json::from_str(r#"{"r": 255, "g": 0, "b": 0}"#).unwrap();
+        assert_eq!(red.a, 255);
+        assert_eq!(serde_json::to_string(&red).unwrap(), r#"{"r":255,"g":0,"b":0}"#);
+
+        let translucent = Color::new_rgba(255, 0, 0, 128);
+        assert_eq!(serde_json::to_string(&translucent).unwrap(), r#"{"r":255,"g":0,"b":0,"a":128}"#);
+        assert_eq!(translucent.rotate_hue(120.0).a, 128);
+    }
+
+    /// Tests compositing a translucent color over an opaque background.
+    #[test]
+    fn test_over() {
+        let white = Color::new(255, 255, 255);
+        assert_eq!(Color::new_rgba(255, 0, 0, 128).over(&white), Color::new(255, 127, 127));
+
+        let opaque = Color::new(10, 20, 30);
+        assert_eq!(opaque.over(&white), opaque);
+        assert_eq!(Color::new_rgba(255, 0, 0, 0).over(&white), white);
+    }
+
+    /// Tests the Android resource output, including the leading alpha byte.
+    #[test]
+    fn test_to_android_xml() {
+        let mut colors = load_colors_from_file(SAMPLE_JSON).unwrap();
+        colors.insert("overlay".to_string(), Color::new_rgba(0, 0, 0, 128));
+
+        let xml = to_android_xml(&colors);
+        assert_eq!(xml, concat!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n",
+            "<resources>\n",
+            "    <color name=\"navy\">#FF000080</color>\n",
+            "    <color name=\"overlay\">#80000000</color>\n",
+            "    <color name=\"red\">#FFFF0000</color>\n",
+            "</resources>\n",
+        ));
+    }
+
+    /// Tests merging maps with and without overwriting.
+    #[test]
+    fn test_merge_colors() {
+        let other = load_colors_from_file(r#"{"red": {"r": 200, "g": 0, "b": 0}, "lime": {"r": 0, "g": 255, "b": 0}}"#).unwrap();
+
+        let mut kept = load_colors_from_file(SAMPLE_JSON).unwrap();
+        merge_colors(&mut kept, &other, false);
+        assert_eq!(kept.len(), 3);
+        assert_eq!(kept["red"], Color::new(255, 0, 0));
+
+        let mut replaced = load_colors_from_file(SAMPLE_JSON).unwrap();
+        merge_colors(&mut replaced, &other, true);
+        assert_eq!(replaced["red"], Color::new(200, 0, 0));
+    }
+
+    /// Tests layering a user palette overriding "red" over the builtin colors.
+    #[test]
+    fn test_color_loader_layers() {
+        let path = write_temp_file("loader.json", r#"{"red": {"r": 200, "g": 0, "b": 0}, "brand": {"r": 0, "g": 82, "b": 204}}"#);
+        let loader = ColorLoader::new().with_builtin().with_file(&path);
+
+        let colors = loader.clone().overwrite(true).load().unwrap();
+        assert_eq!(colors["red"], Color::new(200, 0, 0));
+        assert_eq!(colors["brand"], Color::new(0, 82, 204));
+        assert_eq!(colors["navy"], Color::new(0, 0, 128));
+
+        let colors = loader.overwrite(false).load().unwrap();
+        assert_eq!(colors["red"], Color::new(255, 0, 0));
+        fs::remove_file(&path).unwrap();
+
+        let missing = ColorLoader::new().with_builtin().with_file(&path).load();
+        assert!(matches!(missing, Err(NamedColorsError::Io(_))));
+    }
+
+    /// Tests membership checks for existing and missing names.
+    #[test]
+    fn test_has_color() {
+        let colors = load_colors_from_file(SAMPLE_JSON).unwrap();
+        assert!(has_color(&colors, "navy"));
+        assert!(has_color(&colors, "NAVY"));
+        assert!(!has_color(&colors, "mauve"));
+    }
+
+    /// Tests diffing maps with added, removed and changed colors.
+    #[test]
+    fn test_diff_color_maps() {
+        let before = load_colors_from_file(SAMPLE_JSON).unwrap();
+        let after = load_colors_from_file(r#"{"red": {"r": 200, "g": 0, "b": 0}, "teal": {"r": 0, "g": 128, "b": 128}}"#).unwrap();
+
+        let diff = diff_color_maps(&before, &after);
+        assert_eq!(diff.only_in_a, vec!["navy"]);
+        assert_eq!(diff.only_in_b, vec!["teal"]);
+        assert_eq!(diff.changed, vec![("red".to_string(), Color::new(255, 0, 0), Color::new(200, 0, 0))]);
+        assert!(!diff.is_empty());
+        assert!(diff_color_maps(&before, &before).is_empty());
+    }
+
+    /// Tests clamping of overflowing and negative channels.
+    #[test]
+    fn test_from_i32_clamps() {
+        assert_eq!(Color::from_i32(200 + 100, -10, 128), Color::new(255, 0, 128));
+        assert_eq!(Color::from_i32(i32::MIN, i32::MAX, 0), Color::new(0, 255, 0));
+    }
+
+    /// Tests replacing the alpha channel.
+    #[test]
+    fn test_with_alpha() {
+        let navy = Color::new(0, 0, 128);
+        let overlay = navy.with_alpha(64);
+        assert_eq!((overlay.r, overlay.g, overlay.b, overlay.a), (0, 0, 128, 64));
+        assert_eq!(overlay.opaque(), navy);
+    }
+
+    /// Tests the HSV conversions against a few reference colors.
+    #[test]
+    fn test_hsv_conversions() {
+        assert_eq!(Color::new(255, 0, 0).to_hsv(), (0.0, 1.0, 1.0));
+        assert_eq!(Color::from_hsv(120.0, 1.0, 1.0), Color::new(0, 255, 0));
+        assert_eq!(Color::from_hsv(0.0, 0.0, 0.5), Color::new(128, 128, 128));
+
+        let color = Color::new(70, 130, 180);
+        let (h, s, v) = color.to_hsv();
+        assert_eq!(Color::from_hsv(h, s, v), color);
+    }
+
+    /// Tests parsing `hsl()` and `hsv()` strings.
+    #[test]
+    fn test_from_hsl_hsv_str() {
+        assert_eq!(Color::from_hsl_str("hsl(0,100%,50%)").unwrap(), Color::new(255, 0, 0));
+        assert_eq!(Color::from_hsl_str(" HSL(120deg 100% 25%) ").unwrap(), Color::new(0, 128, 0));
+        assert_eq!(Color::from_hsv_str("hsv(240, 100%, 100%)").unwrap(), Color::new(0, 0, 255));
+
+        for malformed in ["hsl(0, 100, 50%)", "hsl(0, 100%)", "hsl(0, 100%, 50%", "rgb(0, 100%, 50%)", "hsl(red, 1%, 5%)", "hsl(0, 150%, 50%)"] {
+            assert!(matches!(Color::from_hsl_str(malformed), Err(NamedColorsError::InvalidFormat(_))), "{}", malformed);
+        }
+    }
+
+    /// Tests that black and white come first among the accessible pairs.
+    #[test]
+    fn test_accessible_pairs() {
+        let mut colors = HashMap::new();
+        colors.insert("black".to_string(), Color::new(0, 0, 0));
+        colors.insert("white".to_string(), Color::new(255, 255, 255));
+        colors.insert("gray".to_string(), Color::new(128, 128, 128));
+        colors.insert("silver".to_string(), Color::new(192, 192, 192));
+
+        let pairs = accessible_pairs(&colors, 4.5);
+        assert_eq!((pairs[0].0.as_str(), pairs[0].1.as_str()), ("black", "white"));
+        assert!((pairs[0].2 - 21.0).abs() < 0.01);
+        assert!(pairs.iter().all(|(_, _, ratio)| *ratio >= 4.5));
+        assert!(!pairs.iter().any(|(a, b, _)| a == "gray" && b == "silver"));
+    }
+
+    /// Tests round-tripping a map through the binary format.
+    #[test]
+    fn test_binary_round_trip() {
+        let mut colors = builtin_colors().unwrap();
+        colors.insert("glass_blue".to_string(), Color::new_rgba(0, 82, 204, 100));
+        colors.insert("grün".to_string(), Color::new(0, 255, 0));
+
+        let bytes = to_bytes(&colors);
+        assert_eq!(&bytes[..4], b"NCB2");
+        assert_eq!(from_bytes(&bytes).unwrap(), colors);
+
+        assert!(matches!(from_bytes(&bytes[..bytes.len() - 1]), Err(NamedColorsError::InvalidFormat(_))));
+        assert!(matches!(from_bytes(b"JSON"), Err(NamedColorsError::InvalidFormat(_))));
+    }
+
+    /// Tests that names longer than 65535 bytes round-trip through the binary format.
+    #[test]
+    fn test_binary_round_trip_long_name() {
+        let mut colors = HashMap::new();
+        colors.insert("x".repeat(70_000), Color::new(1, 2, 3));
+        colors.insert("red".to_string(), Color::new(255, 0, 0));
+
+        let bytes = to_bytes(&colors);
+        assert_eq!(from_bytes(&bytes).unwrap(), colors);
+        assert!(matches!(from_bytes(&bytes[..bytes.len() - 5]), Err(NamedColorsError::InvalidFormat(_))));
+    }
+
+    /// Tests reading a map written in the older `NCB1` layout.
+    #[test]
+    fn test_from_bytes_v1() {
+        let mut bytes = b"NCB1".to_vec();
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.extend_from_slice(&3u16.to_le_bytes());
+        bytes.extend_from_slice(b"red");
+        bytes.extend_from_slice(&[255, 0, 0, 128]);
+
+        let colors = from_bytes(&bytes).unwrap();
+        assert_eq!(colors.len(), 1);
+        assert_eq!(colors["red"], Color::new_rgba(255, 0, 0, 128));
+    }
+
+    /// Tests that an over-long LEB128 length is rejected instead of overflowing.
+    #[test]
+    fn test_from_bytes_length_overflow() {
+        let mut bytes = b"NCB2".to_vec();
+        bytes.extend_from_slice(&[0xff; 11]);
+        bytes.push(0x01);
+        assert!(matches!(from_bytes(&bytes), Err(NamedColorsError::InvalidFormat(_))));
+    }
+
+    /// Tests that the borrowed color can be used with its methods.
+    #[test]
+    fn test_get_color() {
+        let colors = load_colors_from_file(SAMPLE_JSON).unwrap();
+        let navy = get_color(&colors, "Navy").unwrap();
+        assert_eq!(navy.to_hex(), "#000080");
+        assert_eq!(navy.invert(), Color::new(255, 255, 127));
+        assert!(get_color(&colors, "mauve").is_none());
+    }
+
+    /// Tests selecting the reds with a hue range wrapping around 0°.
+    #[test]
+    fn test_colors_in_hue_range_wraparound() {
+        let mut colors = HashMap::new();
+        colors.insert("red".to_string(), Color::new(255, 0, 0));
+        colors.insert("crimson".to_string(), Color::new(220, 20, 60));  // ~348°
+        colors.insert("scarlet".to_string(), Color::new(255, 36, 0));  // ~8°
+        colors.insert("orange".to_string(), Color::new(255, 165, 0));
+        colors.insert("gray".to_string(), Color::new(128, 128, 128));
+
+        let names: Vec<String> = colors_in_hue_range(&colors, 340.0, 10.0).into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, vec!["crimson", "red", "scarlet"]);
+
+        let names: Vec<String> = colors_in_hue_range(&colors, 20.0, 45.0).into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, vec!["orange"]);
+
+        // Spans of a full turn or more select every chromatic color
+        for (min, max) in [(0.0, 360.0), (-10.0, 350.0), (90.0, 900.0)] {
+            let names: Vec<String> = colors_in_hue_range(&colors, min, max).into_iter().map(|(name, _)| name).collect();
+            assert_eq!(names, vec!["crimson", "orange", "red", "scarlet"], "{}..{}", min, max);
+        }
+    }
+
+    /// Tests the GIMP palette header and color lines.
+    #[test]
+    fn test_to_gpl() {
+        let colors = load_colors_from_file(SAMPLE_JSON).unwrap();
+        let gpl = to_gpl(&colors, "Sample");
+        assert_eq!(gpl, "GIMP Palette\nName: Sample\n#\n  0   0 128\tnavy\n255   0   0\tred\n");
+    }
+
+    /// Tests parsing a small GIMP palette, including names with spaces.
+    #[test]
+    fn test_load_colors_from_gpl() {
+        let data = "GIMP Palette\nName: Sample\nColumns: 4\n# A comment\n\n255   0   0\tred\n135 206 235\tSky Blue\n";
+        let colors = load_colors_from_gpl(data).unwrap();
+        assert_eq!(colors.len(), 2);
+        assert_eq!(colors["red"], Color::new(255, 0, 0));
+        assert_eq!(colors["sky_blue"], Color::new(135, 206, 235));
+
+        // Exported palettes load back unchanged
+        let sample = load_colors_from_file(SAMPLE_JSON).unwrap();
+        assert_eq!(load_colors_from_gpl(&to_gpl(&sample, "Sample")).unwrap(), sample);
+
+        assert!(matches!(load_colors_from_gpl("Name: Sample\n"), Err(NamedColorsError::InvalidLine { line: 1, .. })));
+        assert!(matches!(load_colors_from_gpl("GIMP Palette\n255 0\tred\n"), Err(NamedColorsError::InvalidLine { line: 2, .. })));
+    }
+
+    /// Tests the dominant channel, including ties.
+    #[test]
+    fn test_dominant_channel() {
+        assert_eq!(Color::new(220, 20, 60).dominant_channel(), Channel::Red);
+        assert_eq!(Color::new(0, 0, 128).dominant_channel(), Channel::Blue);
+        assert_eq!(Color::new(0, 200, 200).dominant_channel(), Channel::Green);
+        assert_eq!(Color::new(128, 128, 128).dominant_channel(), Channel::Red);
+    }
+
+    /// Tests loading colors from a reader, and the mapping of read failures.
+    #[test]
+    fn test_load_colors_from_reader() {
+        let colors = load_colors_from_reader(std::io::Cursor::new(SAMPLE_JSON.as_bytes())).unwrap();
+        assert_eq!(colors, load_colors_from_file(SAMPLE_JSON).unwrap());
+
+        struct FailingReader;
+        impl Read for FailingReader {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::new(std::io::ErrorKind::BrokenPipe, "stream closed"))
+            }
+        }
+        assert!(matches!(load_colors_from_reader(FailingReader), Err(NamedColorsError::Io(_))));
+        assert!(matches!(load_colors_from_reader(&b"{"[..]), Err(NamedColorsError::ParseError(_))));
+    }
+
+    /// Tests parsing `rgb()` and `rgba()` strings.
+    #[test]
+    fn test_from_rgb_str() {
+        assert_eq!(Color::from_rgb_str("rgb(255, 0, 0)").unwrap(), Color::new(255, 0, 0));
+        assert_eq!(Color::from_rgb_str("RGB(0% 100% 0%)").unwrap(), Color::new(0, 255, 0));
+        assert_eq!(Color::from_rgb_str("rgba(0, 0, 255, 0.5)").unwrap(), Color::new_rgba(0, 0, 255, 128));
+        assert_eq!(Color::from_rgb_str("rgb(0 0 255 / 50%)").unwrap(), Color::new_rgba(0, 0, 255, 128));
+        assert!(Color::from_rgb_str("rgb(256, 0, 0)").is_err());
+        assert!(Color::from_rgb_str("rgb(0, 0)").is_err());
+    }
+
+    /// Tests the clap-compatible argument parser with a name, a hex code and invalid input.
+    #[cfg(feature = "clap")]
+    #[test]
+    fn test_parse_color_arg() {
+        assert_eq!(parse_color_arg("red"), Ok(Color::new(255, 0, 0)));
+        assert_eq!(parse_color_arg("#00ff00"), Ok(Color::new(0, 255, 0)));
+        assert_eq!(parse_color_arg("rgb(0, 0, 255)"), Ok(Color::new(0, 0, 255)));
+        assert!(parse_color_arg("not-a-color").unwrap_err().contains("not a known color name"));
+    }
+
+    /// Tests that a weighted mix leans towards the heavier color.
+    #[test]
+    fn test_mix_weighted() {
+        let red = Color::new(255, 0, 0);
+        let blue = Color::new(0, 0, 255);
+
+        let mixed = mix_weighted(&[(red, 2.0), (blue, 1.0)]).unwrap();
+        assert!(mixed.r > mixed.b, "{:?} should lean red", mixed);
+        assert_eq!(mixed.g, 0);
+        assert_eq!(mix_weighted(&[(red, 1.0), (blue, 1.0)]).unwrap(), average_color(
+            &HashMap::from([("red".to_string(), red), ("blue".to_string(), blue)]),
+            &["red", "blue"],
+        ).unwrap());
+
+        assert!(matches!(mix_weighted(&[(red, 0.0), (blue, 0.0)]), Err(NamedColorsError::InvalidWeights)));
+        assert!(matches!(mix_weighted(&[(red, -1.0), (blue, 2.0)]), Err(NamedColorsError::InvalidWeights)));
+        assert!(matches!(mix_weighted(&[]), Err(NamedColorsError::NoColors)));
+    }
+
+    /// Tests that the sorted serialization lists names alphabetically and round-trips.
+    #[test]
+    fn test_save_colors_to_string_sorted() {
+        let colors = load_colors_from_file(r#"{"navy": {"r": 0, "g": 0, "b": 128}, "azure": {"r": 240, "g": 255, "b": 255}, "red": {"r": 255, "g": 0, "b": 0}}"#).unwrap();
+        let json = save_colors_to_string_sorted(&colors).unwrap();
+
+        let positions: Vec<usize> = ["\"azure\"", "\"navy\"", "\"red\""].iter().map(|key| json.find(key).unwrap()).collect();
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]), "{}", json);
+        assert_eq!(json, save_colors_to_string_sorted(&colors).unwrap());
+        assert_eq!(load_colors_from_file(&json).unwrap(), colors);
+    }
+
+    /// Tests `Color::distance` for the Euclidean and redmean metrics on the same pair.
+    #[test]
+    fn test_distance_metrics() {
+        let red = Color::new(255, 0, 0);
+        let black = Color::new(0, 0, 0);
+
+        assert_eq!(red.distance(&red, DistanceMetric::Euclidean), 0.0);
+        assert_eq!(red.distance(&black, DistanceMetric::Euclidean), 255.0);
+        assert_eq!(red.distance(&black, DistanceMetric::Euclidean), black.distance(&red, DistanceMetric::Euclidean));
+
+        // Redmean weights a red difference by 2 + 127.5 / 256 between red and black
+        let weighted = red.distance(&black, DistanceMetric::WeightedEuclidean);
+        assert!((weighted - 255.0 * (2.0f32 + 127.5 / 256.0).sqrt()).abs() < 0.01, "{}", weighted);
+        assert!(weighted > red.distance(&black, DistanceMetric::Euclidean));
+    }
+
+    /// Tests absolute brightness steps, including clamping at both ends.
+    #[test]
+    fn test_adjust_brightness() {
+        let color = Color::new(100, 150, 220);
+
+        assert_eq!(color.adjust_brightness(50), Color::new(150, 200, 255));
+        assert_eq!(color.adjust_brightness(-120), Color::new(0, 30, 100));
+        assert_eq!(color.adjust_brightness(i16::MAX), Color::new(255, 255, 255));
+        assert_eq!(color.with_alpha(10).adjust_brightness(0).a, 10);
+    }
+
+    /// Tests that ranking by similarity puts the exact match first and sorts by distance.
+    #[test]
+    fn test_sort_by_similarity() {
+        let colors = builtin_colors().unwrap();
+        let ranked = sort_by_similarity(&colors, &Color::new(0, 0, 128), DistanceMetric::CIE76);
+
+        assert_eq!(ranked.len(), colors.len());
+        assert_eq!(ranked[0].1, Color::new(0, 0, 128));
+        assert_eq!(ranked[0].2, 0.0);
+        assert!(ranked.windows(2).all(|pair| pair[0].2 <= pair[1].2));
+    }
+
+    /// Tests that a bad entry is reported with its color name.
+    #[test]
+    fn test_load_colors_with_context() {
+        assert_eq!(load_colors_with_context(SAMPLE_JSON).unwrap(), load_colors_from_file(SAMPLE_JSON).unwrap());
+
+        let json = r#"{"red": {"r": 255, "g": 0, "b": 0}, "mauve": {"r": 224, "g": "lots", "b": 255}}"#;
+        let err = load_colors_with_context(json).unwrap_err();
+        assert!(matches!(&err, NamedColorsError::InvalidColor { name, .. } if name == "mauve"));
+        assert!(err.to_string().starts_with("failed to parse color 'mauve': "), "{}", err);
+    }
+
+    /// Tests creating, resolving and chaining aliases, and that they follow updates of the target.
+    #[test]
+    fn test_add_alias() {
+        let mut colors = load_colors_from_file(SAMPLE_JSON).unwrap();
+
+        add_alias(&colors, "Admiral Blue", "navy").unwrap();
+        add_alias(&colors, "deep sea", "admiral-blue").unwrap();
+        assert_eq!(alias_target("deep_sea").as_deref(), Some("navy"));
+        assert_eq!(get_color(&colors, "admiral_blue"), Some(&Color::new(0, 0, 128)));
+        assert!(has_color(&colors, "Deep Sea"));
+        assert_eq!(get_color(&colors, "red"), Some(&Color::new(255, 0, 0)));
+
+        colors.insert("navy".to_string(), Color::new(0, 0, 100));
+        assert_eq!(get_color(&colors, "deep_sea"), Some(&Color::new(0, 0, 100)));
+
+        assert!(matches!(add_alias(&colors, "ghost", "mauve"), Err(NamedColorsError::ColorNotFound(_))));
+        assert!(matches!(add_alias(&colors, "RED", "navy"), Err(NamedColorsError::DuplicateColor(_))));
+        assert!(matches!(add_alias(&colors, "deep-sea", "red"), Err(NamedColorsError::DuplicateColor(_))));
+        assert_eq!(get_color(&colors, "ghost"), None);
+        let err = add_alias(&colors, "__", "navy").unwrap_err();
+        assert!(matches!(&err, NamedColorsError::InvalidName(name) if name == "__"));
+        assert_eq!(err.to_string(), "invalid color name '__'");
+    }
+
+    /// Tests that oranges score warm, blues score cool and grays stay neutral.
+    #[test]
+    fn test_palette_warmth() {
+        let colors = builtin_colors().unwrap();
+
+        let warm = palette_warmth(&colors, &["orange", "coral", "gold"]).unwrap();
+        let cool = palette_warmth(&colors, &["blue", "navy", "steelblue"]).unwrap();
+        assert!((0.5..=1.0).contains(&warm), "{}", warm);
+        assert!((-1.0..=-0.5).contains(&cool), "{}", cool);
+        assert_eq!(palette_warmth(&colors, &["gray", "white"]).unwrap(), 0.0);
+
+        assert!(matches!(palette_warmth(&colors, &["orange", "mauve"]), Err(NamedColorsError::ColorNotFound(name)) if name == "mauve"));
+        assert!(matches!(palette_warmth(&colors, &[]), Err(NamedColorsError::NoColors)));
+    }
+
+    /// Tests parsing hex codes with an alpha part, including the 4-digit shorthand.
+    #[test]
+    fn test_from_hex_with_alpha() {
+        assert_eq!(Color::from_hex("#f008").unwrap(), Color::from_hex("#ff000088").unwrap());
+        assert_eq!(Color::from_hex("#ff000088").unwrap(), Color::new_rgba(255, 0, 0, 0x88));
+        assert_eq!(Color::from_hex("0000FFcc").unwrap(), Color::new_rgba(0, 0, 255, 0xcc));
+        assert_eq!(Color::from_hex("#f00").unwrap().a, 255);
+        assert!(Color::from_hex("#f00g").is_err());
+    }
+
+    /// Tests the membership and values of the CSS color tiers.
+    #[test]
+    fn test_css_color_tiers() {
+        let basic = css_basic_colors();
+        let extended = css_extended_colors();
+
+        assert_eq!(basic.len(), 16);
+        assert_eq!(extended.len(), 148);
+        assert_eq!(basic["fuchsia"], Color::new(255, 0, 255));
+        assert!(!basic.contains_key("rebeccapurple"));
+        assert_eq!(extended["rebeccapurple"], Color::new(102, 51, 153));
+        assert!(basic.iter().all(|(name, color)| extended.get(name) == Some(color)));
+    }
+
+    /// Tests collecting into a `ColorMap` and iterating back in name order.
+    #[test]
+    fn test_color_map_iteration() {
+        let entries = vec![
+            ("red".to_string(), Color::new(255, 0, 0)),
+            ("azure".to_string(), Color::new(240, 255, 255)),
+            ("navy".to_string(), Color::new(0, 0, 128)),
+        ];
+        let map: ColorMap = entries.clone().into_iter().collect();
+
+        let names: Vec<&str> = map.iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["azure", "navy", "red"]);
+        for (name, color) in &map {
+            assert!(entries.contains(&(name.to_string(), *color)));
+        }
+        assert_eq!(map.get("Navy"), Some(&Color::new(0, 0, 128)));
+
+        let owned: Vec<(String, Color)> = map.clone().into_iter().collect();
+        assert_eq!(owned[2], entries[0]);
+        assert_eq!(ColorMap::from(map.clone().into_hash_map()), map);
+    }
+
+    /// Tests snapping an off-red to the red of a small custom palette.
+    #[test]
+    fn test_map_to_palette() {
+        let palette = HashMap::from([
+            ("brick".to_string(), Color::new(200, 30, 30)),
+            ("sky".to_string(), Color::new(100, 180, 255)),
+            ("grass".to_string(), Color::new(40, 160, 60)),
+        ]);
+
+        for metric in [DistanceMetric::Euclidean, DistanceMetric::CIE94] {
+            assert_eq!(map_to_palette(&palette, &Color::new(230, 20, 40), metric), Some(("brick".to_string(), Color::new(200, 30, 30))));
+        }
+        assert_eq!(map_to_palette(&HashMap::new(), &Color::new(230, 20, 40), DistanceMetric::Euclidean), None);
+    }
+
+    /// Tests packing colors into `u32` values and back.
+    #[test]
+    fn test_u32_packing() {
+        assert_eq!(Color::new(255, 0, 0).to_u32(), 0x00ff_0000);
+        assert_eq!(Color::from_u32(0x00ff_0000), Color::new(255, 0, 0));
+        assert_eq!(Color::from_u32(0x1234_5678), Color::new(0x34, 0x56, 0x78));
+
+        let color = Color::new_rgba(18, 52, 86, 120);
+        assert_eq!(color.to_u32_argb(), 0x7812_3456);
+        assert_eq!(Color::from_u32_argb(color.to_u32_argb()), color);
+        assert_eq!(Color::from_u32(color.to_u32()), color.opaque());
+    }
+
+    /// Tests that saturated mid-tones score as more vibrant than pastels, dark shades and grays.
+    #[test]
+    fn test_vibrancy() {
+        let red = Color::new(255, 0, 0).vibrancy();
+        let pastel = Color::new(255, 209, 220).vibrancy();
+        let navy = Color::new(0, 0, 128).vibrancy();
+
+        assert!((red - 1.0).abs() < 1e-4, "{}", red);
+        assert!(red > pastel && red > navy, "{} {} {}", red, pastel, navy);
+        assert_eq!(Color::new(128, 128, 128).vibrancy(), 0.0);
+        assert_eq!(Color::new(255, 255, 255).vibrancy(), 0.0);
+    }
+
+    /// Tests that the shared builtin map is parsed once and readable from several threads.
+    #[test]
+    fn test_shared_builtin() {
+        let colors = shared_builtin();
+        assert!(Arc::ptr_eq(&colors, &shared_builtin()));
+
+        let handles: Vec<_> = ["red", "navy"]
+            .iter()
+            .map(|name| {
+                let colors = Arc::clone(&colors);
+                std::thread::spawn(move || colors.get(*name).copied())
+            })
+            .collect();
+        let found: Vec<Option<Color>> = handles.into_iter().map(|handle| handle.join().unwrap()).collect();
+        assert_eq!(found, [Some(Color::new(255, 0, 0)), Some(Color::new(0, 0, 128))]);
+    }
+
+    /// Tests lookups and blending with 8-bit and float channels.
+    #[test]
+    fn test_generic_channels() {
+        let bytes: HashMap<String, ColorT<u8>> = load_colors_from_file(SAMPLE_JSON).unwrap();
+        assert_eq!(get_color(&bytes, "Navy"), Some(&Color::new(0, 0, 128)));
+        assert_eq!(bytes["red"].blend(&bytes["navy"], 0.5), Color::new(128, 0, 64));
+
+        let floats: HashMap<String, ColorF32> = serde_json::from_str(r#"{"hdr_white": {"r": 2.0, "g": 2.0, "b": 2.0}, "red": {"r": 1.0, "g": 0.0, "b": 0.0, "a": 0.5}}"#).unwrap();
+        let white = get_color(&floats, "HDR White").unwrap();
+        assert_eq!(white.a, 1.0);
+        assert_eq!(white.blend(&floats["red"], 0.5), ColorF32::new_rgba(1.5, 1.0, 1.0, 0.75));
+
+        assert_eq!(ColorF32::from(Color::new(255, 0, 51)), ColorF32::new(1.0, 0.0, 0.2));
+        assert_eq!(Color::from(*white), Color::new(255, 255, 255));
+        assert_eq!(serde_json::to_string(&ColorF32::new(1.0, 0.5, 0.0)).unwrap(), r#"{"r":1.0,"g":0.5,"b":0.0}"#);
+    }
+
+    /// Tests finding two nearly identical colors while distinct ones are ignored.
+    #[test]
+    fn test_near_duplicates() {
+        let mut colors = load_colors_from_file(SAMPLE_JSON).unwrap();
+        colors.insert("brand_red".to_string(), Color::new(254, 1, 0));
+        colors.insert("exact_navy".to_string(), Color::new(0, 0, 128));
+
+        let pairs = near_duplicates(&colors, 2.0, DistanceMetric::Euclidean);
+        assert_eq!(pairs.len(), 2);
+        assert_eq!((pairs[0].0.as_str(), pairs[0].1.as_str(), pairs[0].2), ("exact_navy", "navy", 0.0));
+        assert_eq!((pairs[1].0.as_str(), pairs[1].1.as_str()), ("brand_red", "red"));
+        assert_eq!(near_duplicates(&colors, 0.0, DistanceMetric::Euclidean).len(), 1);
+    }
+
+    /// Tests that `to_gl` returns gamma-decoded channels rather than a plain division by 255.
+    #[test]
+    fn test_to_gl() {
+        assert_eq!(Color::new(255, 0, 255).to_gl(), [1.0, 0.0, 1.0, 1.0]);
+
+        let [r, g, b, a] = Color::new_rgba(128, 128, 128, 51).to_gl();
+        assert!((r - 0.2158).abs() < 1e-3, "{}", r);
+        assert!(r < 128.0 / 255.0);
+        assert_eq!((r, g), (g, b));
+        assert_eq!(a, 0.2);
+    }
+
+    /// Tests loading tagged and untagged colors and filtering them by tag.
+    #[test]
+    fn test_colors_with_tag() {
+        let json = r#"{
+            "pink": {"r": 255, "g": 209, "b": 220, "tags": ["pastel"]},
+            "mint": {"r": 189, "g": 252, "b": 201, "tags": ["pastel", "brand"]},
+            "red": {"r": 255, "g": 0, "b": 0}
+        }"#;
+        let colors = load_tagged_colors(json).unwrap();
+
+        assert_eq!(colors["mint"].color, Color::new(189, 252, 201));
+        assert!(colors["red"].tags.is_empty());
+        assert_eq!(colors_with_tag(&colors, "pastel"), ["mint", "pink"]);
+        assert_eq!(colors_with_tag(&colors, "brand"), ["mint"]);
+        assert!(colors_with_tag(&colors, "neon").is_empty());
+        assert_eq!(load_tagged_colors(SAMPLE_JSON).unwrap()["navy"].color, Color::new(0, 0, 128));
+    }
+
+    /// Tests looking up a non-ASCII name in different cases.
+    #[test]
+    fn test_non_ascii_names() {
+        let mut colors = HashMap::new();
+        add_color(&mut colors, "grün", 0, 128, 0).unwrap();
+        assert_eq!(get_color(&colors, "Grün"), Some(&Color::new(0, 128, 0)));
+
+        // Without the `unicode` feature only the ASCII letters are folded
+        #[cfg(not(feature = "unicode"))]
+        assert_eq!(get_color(&colors, "GRÜN"), None);
+        #[cfg(feature = "unicode")]
+        {
+            assert_eq!(get_color(&colors, "GRÜN"), Some(&Color::new(0, 128, 0)));
+            assert_eq!(get_color(&colors, "gru\u{308}n"), Some(&Color::new(0, 128, 0)));
+        }
+    }
+
+    /// Tests that name/hex pairs are sorted and formatted as lowercase hex codes.
+    #[test]
+    fn test_to_name_hex_pairs() {
+        let mut colors = load_colors_from_file(SAMPLE_JSON).unwrap();
+        colors.insert("Azure".to_string(), Color::new(240, 255, 255));
+
+        let pairs = to_name_hex_pairs(&colors);
+        let expected = [("Azure", "#f0ffff"), ("navy", "#000080"), ("red", "#ff0000")];
+        assert_eq!(pairs, expected.map(|(name, hex)| (name.to_string(), hex.to_string())));
+    }
+
+    /// Tests requesting the 3 nearest colors, sorted by distance.
+    #[test]
+    fn test_nearest_colors() {
+        let colors = builtin_colors().unwrap();
+        let nearest = nearest_colors(&colors, 250, 5, 5, 3, DistanceMetric::Euclidean);
+
+        assert_eq!(nearest.len(), 3);
+        assert_eq!((nearest[0].0.as_str(), nearest[0].1), ("red", (255, 0, 0)));
+        assert!(nearest.windows(2).all(|pair| pair[0].2 <= pair[1].2));
+
+        let all = sort_by_similarity(&colors, &Color::new(250, 5, 5), DistanceMetric::Euclidean);
+        let expected: Vec<&str> = all.iter().take(3).map(|entry| entry.0.as_str()).collect();
+        assert_eq!(nearest.iter().map(|entry| entry.0.as_str()).collect::<Vec<_>>(), expected);
+        assert!(nearest_colors(&colors, 0, 0, 0, 0, DistanceMetric::Euclidean).is_empty());
+        assert_eq!(nearest_colors(&load_colors_from_file(SAMPLE_JSON).unwrap(), 0, 0, 0, 5, DistanceMetric::CIE76).len(), 2);
+    }
+
+    /// Tests that adjusting to a target luminance lands close to it and keeps the hue.
+    #[test]
+    fn test_with_luminance() {
+        let base = Color::new(70, 130, 180);
+        for target in [0.05, 0.3, 0.7] {
+            let adjusted = base.with_luminance(target);
+            assert!((adjusted.luminance() - target).abs() < 0.01, "{} -> {}", target, adjusted.luminance());
+            assert!((adjusted.to_hsl().0 - base.to_hsl().0).abs() < 2.0);
+        }
+        assert_eq!(base.with_luminance(0.0), Color::new(0, 0, 0));
+        assert_eq!(base.with_luminance(1.0), Color::new(255, 255, 255));
+    }
+
+    /// Tests chaining transforms, each returning a new color.
+    #[test]
+    fn test_transform_chain() {
+        let red = Color::new_rgba(255, 0, 0, 200);
+        let result = red.rotate_hue(120.0).darken(0.1).desaturate(0.2);
+
+        assert_eq!(result, Color::new_rgba(20, 184, 20, 200));
+        assert_eq!(red.lighten(1.0), Color::new_rgba(255, 255, 255, 200));
+        assert_eq!(red.darken(0.25).lighten(0.25), red);
+    }
+
+    /// Tests parsing a list mixing names, hex codes, an rgb() function and an invalid token.
+    #[test]
+    fn test_parse_color_list() {
+        let colors = load_colors_from_file(SAMPLE_JSON).unwrap();
+        let parsed = parse_color_list(&colors, "Red, #00ff00  rgb(0, 0, 255),mauve,, #12");
+
+        assert_eq!(parsed.len(), 5);
+        assert_eq!(parsed[0].as_ref().unwrap(), &Color::new(255, 0, 0));
+        assert_eq!(parsed[1].as_ref().unwrap(), &Color::new(0, 255, 0));
+        assert_eq!(parsed[2].as_ref().unwrap(), &Color::new(0, 0, 255));
+        assert!(matches!(&parsed[3], Err(NamedColorsError::ColorNotFound(name)) if name == "mauve"));
+        assert!(matches!(&parsed[4], Err(NamedColorsError::InvalidHex(_))));
+        assert!(parse_color_list(&colors, "  ").is_empty());
+    }
+
+    /// Tests every string format on red.
+    #[test]
+    fn test_format() {
+        let red = Color::new(255, 0, 0);
+
+        assert_eq!(red.format(ColorFormat::HexLower), "#ff0000");
+        assert_eq!(red.format(ColorFormat::HexUpper), "#FF0000");
+        assert_eq!(red.format(ColorFormat::Rgb), "rgb(255, 0, 0)");
+        assert_eq!(red.format(ColorFormat::RgbPercent), "rgb(100%, 0%, 0%)");
+        assert_eq!(red.format(ColorFormat::Hsl), "hsl(0, 100%, 50%)");
+        assert_eq!(red.format(ColorFormat::Css), "#ff0000");
+        assert_eq!(red.with_alpha(128).format(ColorFormat::Css), "rgba(255, 0, 0, 0.502)");
+        assert_eq!(Color::new(128, 0, 0).format(ColorFormat::RgbPercent), "rgb(50.2%, 0%, 0%)");
+    }
+
+    /// Tests that light fills get a darker border and dark fills a lighter one.
+    #[test]
+    fn test_border_color() {
+        let light = Color::new(255, 228, 196);
+        let dark = Color::new(0, 0, 128);
+
+        assert!(light.border_color().luminance() < light.luminance() - 0.09);
+        assert!(dark.border_color().luminance() > dark.luminance() + 0.09);
+        assert_eq!(Color::new(255, 255, 255).border_color().to_hsl().1, 0.0);  // Gray borders for gray fills
+    }
+
+    /// Tests the tolerant comparison on colors one unit apart.
+    #[test]
+    fn test_approx_eq() {
+        let color = Color::new(100, 150, 200);
+        let close = Color::new(101, 149, 201);
+
+        assert!(color.approx_eq(&close, 1));
+        assert!(!color.approx_eq(&close, 0));
+        assert!(color.approx_eq(&color, 0));
+        assert!(!color.approx_eq(&color.with_alpha(250), 1));
+        let (h, s, l) = Color::new(70, 130, 180).to_hsl();
+        assert!(Color::from_hsl(h, s, l).approx_eq(&Color::new(70, 130, 180), 1));
+    }
+
+    /// Tests loading a directory of palettes namespaced by file name, and reporting the broken file.
+    #[test]
+    fn test_load_palettes_from_dir() {
+        let dir = std::env::temp_dir().join(format!("named_colors_{}_palettes", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("material.json"), r#"{"red": {"r": 244, "g": 67, "b": 54}}"#).unwrap();
+        fs::write(dir.join("brand.json"), SAMPLE_JSON).unwrap();
+        fs::write(dir.join("notes.txt"), "not a palette").unwrap();
+
+        let colors = load_palettes_from_dir(&dir).unwrap();
+        assert_eq!(colors.len(), 3);
+        assert_eq!(colors["material_red"], Color::new(244, 67, 54));
+        assert_eq!(colors["brand_navy"], Color::new(0, 0, 128));
+
+        fs::write(dir.join("broken.json"), "{").unwrap();
+        let err = load_palettes_from_dir(&dir).unwrap_err();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(err, NamedColorsError::InvalidFile { path, .. } if path.ends_with("broken.json")));
+    }
+
+    /// Tests that red and cyan average to the linear-space mid gray.
+    #[test]
+    fn test_palette_average() {
+        let colors = HashMap::from([("red".to_string(), Color::new(255, 0, 0)), ("cyan".to_string(), Color::new(0, 255, 255))]);
+
+        assert_eq!(palette_average(&colors), Some(Color::from_linear(0.5, 0.5, 0.5)));
+        assert_eq!(palette_average(&colors), Some(Color::new(188, 188, 188)));
+        assert_eq!(palette_average(&HashMap::new()), None);
+    }
+
+    /// Tests deserializing normalized float channels, integer channels and an out-of-range float.
+    #[test]
+    fn test_normalized_serde() {
+        let parse = |json: &str| normalized_serde::map::deserialize(&mut serde_json::Deserializer::from_str(json));
+
+        let colors = parse(r#"{"orange": {"r": 1.0, "g": 0.5, "b": 0.0, "a": 0.2}, "navy": {"r": 0, "g": 0, "b": 128}}"#).unwrap();
+        assert_eq!(colors["orange"], Color::new_rgba(255, 128, 0, 51));
+        assert_eq!(colors["navy"], Color::new(0, 0, 128));
+
+        let err = parse(r#"{"bright": {"r": 1.5, "g": 0.0, "b": 0.0}}"#).unwrap_err();
+        assert!(err.to_string().contains("1.5 is outside 0.0..=1.0"), "{}", err);
+        assert!(parse(r#"{"red": {"r": 300, "g": 0, "b": 0}}"#).is_err());
+    }
+
+    /// Tests renaming a color, renaming a missing one and renaming onto an existing name.
+    #[test]
+    fn test_rename_color() {
+        let mut colors = load_colors_from_file(SAMPLE_JSON).unwrap();
+
+        rename_color(&mut colors, "Navy", "Dark Blue").unwrap();
+        assert_eq!(colors.get("dark_blue"), Some(&Color::new(0, 0, 128)));
+        assert!(!colors.contains_key("navy"));
+
+        assert!(matches!(rename_color(&mut colors, "navy", "midnight"), Err(NamedColorsError::ColorNotFound(_))));
+        assert!(matches!(rename_color(&mut colors, "red", "dark-blue"), Err(NamedColorsError::DuplicateColor(name)) if name == "dark_blue"));
+        assert!(matches!(rename_color(&mut colors, "red", " - "), Err(NamedColorsError::InvalidName(_))));
+        assert_eq!(colors.len(), 2);
+    }
+
+    /// Tests requesting pages of a known map, including the last partial page and out-of-range pages.
+    #[test]
+    fn test_paginate_colors() {
+        let colors: HashMap<String, Color> = ["a", "b", "c", "d", "e"]
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.to_string(), Color::new(i as u8, 0, 0)))
+            .collect();
+
+        let names = |page: usize| paginate_colors(&colors, page, 2).into_iter().map(|(name, _)| name).collect::<Vec<_>>();
+        assert_eq!(names(0), ["a", "b"]);
+        assert_eq!(names(1), ["c", "d"]);
+        assert_eq!(names(2), ["e"]);
+        assert!(names(3).is_empty());
+        assert_eq!(paginate_colors(&colors, 1, 2)[0].1, Color::new(2, 0, 0));
+        assert!(paginate_colors(&colors, 0, 0).is_empty());
+        assert!(paginate_colors(&colors, usize::MAX, 2).is_empty());
+    }
+
+    /// Tests classifying colors into basic color terms.
+    #[test]
+    fn test_closest_basic_name() {
+        assert_eq!(Color::new(0, 0, 128).closest_basic_name(), "blue");
+        assert_eq!(Color::new(245, 245, 220).closest_basic_name(), "brown");
+        assert_eq!(Color::new(211, 211, 211).closest_basic_name(), "gray");
+        assert_eq!(Color::new(255, 165, 0).closest_basic_name(), "orange");
+        assert_eq!(Color::new(255, 192, 203).closest_basic_name(), "pink");
+        assert_eq!(Color::new(220, 20, 60).closest_basic_name(), "red");
+        assert_eq!(Color::new(34, 139, 34).closest_basic_name(), "green");
+        assert_eq!(Color::new(10, 10, 10).closest_basic_name(), "black");
+        assert_eq!(Color::new(250, 250, 250).closest_basic_name(), "white");
+    }
+
+    /// Tests loading a commented palette with trailing commas that the plain loader rejects.
+    #[cfg(feature = "jsonc")]
+    #[test]
+    fn test_load_colors_from_jsonc() {
+        let jsonc = r#"{
+            // Primary colors
+            "red": {"r": 255, "g": 0, "b": 0}, /* the brand red */
+            "navy": {"r": 0, "g": 0, "b": 128,},
+            "url // not a comment": {"r": 1, "g": 2, "b": 3},
+        }"#;
+
+        assert!(load_colors_from_file(jsonc).is_err());
+        let colors = load_colors_from_jsonc(jsonc).unwrap();
+        assert_eq!(colors.len(), 3);
+        assert_eq!(colors["navy"], Color::new(0, 0, 128));
+        assert!(colors.contains_key("url // not a comment"));
+        assert!(load_colors_from_jsonc("{ /* open").is_err());
+    }
+
+    /// Tests that white lands in the light set and black in the dark set.
+    #[test]
+    fn test_split_light_dark() {
+        let colors = builtin_colors().unwrap();
+        let (light, dark) = split_light_dark(&colors);
+
+        assert!(light.contains_key("white") && !dark.contains_key("white"));
+        assert!(dark.contains_key("black") && !light.contains_key("black"));
+        assert!(dark.contains_key("navy"));
+        assert_eq!(light.len() + dark.len(), colors.len());
+    }
+
+    /// Tests premultiplying a 50% alpha color and undoing it.
+    #[test]
+    fn test_premultiply() {
+        let color = Color::new_rgba(255, 128, 0, 128);
+        let premultiplied = color.premultiply();
+
+        assert_eq!(premultiplied, Color::new_rgba(128, 64, 0, 128));
+        assert!(premultiplied.unpremultiply().approx_eq(&color, 1));
+        assert_eq!(Color::new(10, 20, 30).premultiply(), Color::new(10, 20, 30));
+        assert_eq!(Color::new_rgba(10, 20, 30, 0).premultiply(), Color::new_rgba(0, 0, 0, 0));
+        assert_eq!(Color::new_rgba(10, 20, 30, 0).unpremultiply(), Color::new_rgba(0, 0, 0, 0));
+    }
+
+    /// Tests the HTML preview swatches, their text colors and the escaping of names.
+    #[test]
+    fn test_to_html_preview() {
+        let mut colors = load_colors_from_file(SAMPLE_JSON).unwrap();
+        colors.insert("<white>".to_string(), Color::new(255, 255, 255));
+        let html = to_html_preview(&colors);
+
+        assert!(html.contains(r#"<div style="background: #ff0000; color: #000000;"#), "{}", html);
+        assert!(html.contains(r#"background: #000080; color: #ffffff;"#));
+        assert!(html.contains("&lt;white&gt;<br>#ffffff</div>"));
+        assert!(html.find("navy").unwrap() < html.find("red<br>").unwrap());
+    }
+
+    /// Tests that the nearest index agrees with the brute-force search on the builtin colors.
+    #[test]
+    fn test_nearest_index() {
+        let colors = builtin_colors().unwrap();
+        let index = NearestIndex::build(&colors);
+
+        for (r, g, b) in [(250, 5, 5), (0, 0, 0), (12, 200, 180), (128, 128, 128), (255, 255, 250)] {
+            assert_eq!(index.query(r, g, b), nearest_color_with(&colors, r, g, b, DistanceMetric::Euclidean));
+        }
+        assert_eq!(NearestIndex::build(&HashMap::new()).query(0, 0, 0), None);
+    }
+
+    /// Tests the nearest index on a larger generated palette with many queries.
+    #[test]
+    fn test_nearest_index_large() {
+        // Deterministic pseudo-random channels from a linear congruential generator
+        let mut state: u32 = 12345;
+        let mut next = || {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (state >> 16) as u8
+        };
+        let colors: HashMap<String, Color> = (0..5000).map(|i| (format!("c{}", i), Color::new(next(), next(), next()))).collect();
+        let queries: Vec<(u8, u8, u8)> = (0..200).map(|_| (next(), next(), next())).collect();
+
+        let start = std::time::Instant::now();
+        let index = NearestIndex::build(&colors);
+        let results: Vec<_> = queries.iter().map(|&(r, g, b)| index.query(r, g, b)).collect();
+        assert!(start.elapsed() < Duration::from_secs(10));
+
+        for (&(r, g, b), result) in queries.iter().zip(results) {
+            assert_eq!(result, nearest_color_with(&colors, r, g, b, DistanceMetric::Euclidean));
+        }
+    }
+
+    /// Tests that the shade/tint ramp runs from black through the base color to white.
+    #[test]
+    fn test_shades_and_tints() {
+        let base = Color::new(70, 130, 180);
+        let ramp = base.shades_and_tints(9);
+
+        assert_eq!(ramp.len(), 9);
+        assert_eq!(ramp[0], Color::new(0, 0, 0));
+        assert_eq!(ramp[8], Color::new(255, 255, 255));
+        assert!(ramp.contains(&base));
+        assert!(ramp.windows(2).all(|pair| pair[0].luminance() < pair[1].luminance()));
+        assert_eq!(base.shades_and_tints(1), [base]);
+        assert!(base.shades_and_tints(0).is_empty());
+    }
+
+    /// Tests that black or white wins against a mid gray, and that an empty map gives `None`.
+    #[test]
+    fn test_most_contrasting() {
+        let colors = builtin_colors().unwrap();
+
+        let (name, ratio) = most_contrasting(&colors, &Color::new(128, 128, 128)).unwrap();
+        assert_eq!(name, "black");
+        assert!((ratio - Color::new(0, 0, 0).contrast_ratio(&Color::new(128, 128, 128))).abs() < 1e-6);
+        assert_eq!(most_contrasting(&colors, &Color::new(0, 0, 128)).unwrap().0, "white");
+        assert_eq!(most_contrasting(&HashMap::new(), &Color::new(0, 0, 0)), None);
+    }
+
+    /// Tests batch naming with exact and approximate inputs, keeping the input order.
+    #[test]
+    fn test_names_for_rgbs() {
+        let mut colors = load_colors_from_file(SAMPLE_JSON).unwrap();
+        colors.insert("crimson_red".to_string(), Color::new(255, 0, 0));
+        colors.insert("white".to_string(), Color::new(255, 255, 255));
+
+        let names = names_for_rgbs(&colors, &[(0, 0, 128), (250, 10, 10), (255, 0, 0), (240, 240, 250)], DistanceMetric::CIE76);
+        assert_eq!(names, ["navy", "crimson_red", "crimson_red", "white"].map(|name| Some(name.to_string())));
+        assert_eq!(names_for_rgbs(&HashMap::new(), &[(0, 0, 0), (1, 2, 3)], DistanceMetric::Euclidean), [None, None]);
+        assert!(names_for_rgbs(&colors, &[], DistanceMetric::Euclidean).is_empty());
+    }
+
+    /// Tests reading the line and column of a JSON error.
+    #[test]
+    fn test_error_location() {
+        let err = load_colors_from_file("{\n  \"red\": {\"r\": 255, \"g\": 0, \"b\": 0},\n  \"navy\": {\"r\": 0 \"g\": 0}\n}").unwrap_err();
+        assert_eq!(err.location().map(|(line, _)| line), Some(3));
+
+        // Entries parsed from a `Value` have no position
+        assert_eq!(load_colors_with_context(r#"{"red": {"r": "x", "g": 0, "b": 0}}"#).unwrap_err().location(), None);
+        assert_eq!(NamedColorsError::NoColors.location(), None);
+    }
+
+    /// Tests the redmean distance against plain Euclidean on a red/pink pair.
+    #[test]
+    fn test_redmean_distance() {
+        let red = Color::new(255, 0, 0);
+        let pink = Color::new(255, 192, 203);
+
+        let redmean = red.redmean_distance(&pink);
+        let euclidean = red.distance(&pink, DistanceMetric::Euclidean);
+        // With both reds at 255, green differences count 4x and blue ones 2x
+        assert!((redmean - (4.0f32 * 192.0 * 192.0 + 2.0 * 203.0 * 203.0).sqrt()).abs() < 0.01, "{}", redmean);
+        assert!(redmean > euclidean);
+        assert_eq!(redmean, red.distance(&pink, DistanceMetric::WeightedEuclidean));
+        assert_eq!(red.redmean_distance(&red), 0.0);
+    }
+
+    /// Tests resolving references, including chains, missing targets and cycles.
+    #[test]
+    fn test_load_colors_with_refs() {
+        let json = r#"{"brand_primary": {"ref": "red"}, "button": {"ref": "brand_primary"}, "red": {"r": 255, "g": 0, "b": 0}}"#;
+        let colors = load_colors_with_refs(json).unwrap();
+        assert_eq!(colors.len(), 3);
+        assert_eq!(colors["brand_primary"], Color::new(255, 0, 0));
+        assert_eq!(colors["button"], Color::new(255, 0, 0));
+
+        let missing = load_colors_with_refs(r#"{"accent": {"ref": "mauve"}}"#).unwrap_err();
+        assert!(matches!(&missing, NamedColorsError::UnresolvedRef { name, target } if name == "accent" && target == "mauve"));
+
+        let cyclic = load_colors_with_refs(r#"{"a": {"ref": "b"}, "b": {"ref": "c"}, "c": {"ref": "a"}}"#).unwrap_err();
+        assert!(matches!(&cyclic, NamedColorsError::CyclicRef(names) if names == &["a", "b", "c", "a"]), "{}", cyclic);
+        assert!(matches!(load_colors_with_refs(r#"{"a": {"ref": "a"}}"#), Err(NamedColorsError::CyclicRef(_))));
+    }
+
+    /// Tests clamping channels into a broadcast-safe range.
+    #[test]
+    fn test_clamp_to_range() {
+        assert_eq!(Color::new(0, 0, 0).clamp_to_range(16, 235), Color::new(16, 16, 16));
+        assert_eq!(Color::new(255, 128, 3).clamp_to_range(16, 235), Color::new(235, 128, 16));
+        assert_eq!(Color::new_rgba(255, 255, 255, 0).clamp_to_range(16, 240).a, 0);
+    }
+
+    /// Tests the grid dimensions and that the top row is lighter than the bottom one.
+    #[test]
+    fn test_arrange_grid() {
+        let colors = builtin_colors().unwrap();
+        let grid = arrange_grid(&colors, 10);
+
+        assert_eq!(grid.len(), 14);  // 133 colors
+        assert_eq!(grid[0].len(), 10);
+        assert_eq!(grid.iter().map(Vec::len).sum::<usize>(), colors.len());
+
+        let average_lightness = |row: &Vec<(String, Color)>| row.iter().map(|(_, color)| color.to_hsl().2).sum::<f32>() / row.len() as f32;
+        assert!(average_lightness(&grid[0]) > average_lightness(&grid[grid.len() - 1]));
+        assert!(arrange_grid(&colors, 0).is_empty());
+
+        // 5 colors in 4 columns: the first column has 2 colors, the others 1
+        let five: HashMap<String, Color> = generate_distinct_colors(5).into_iter().enumerate().map(|(i, color)| (format!("c{}", i), color)).collect();
+        let grid = arrange_grid(&five, 4);
+        assert_eq!(grid.iter().map(Vec::len).collect::<Vec<_>>(), vec![4, 1]);
+        let grid = arrange_grid(&colors, 4);
+        assert_eq!(grid.len(), 34);
+        assert!(grid[..33].iter().all(|row| row.len() == 4));
+        assert_eq!(grid[33].len(), 1);
+        assert_eq!(arrange_grid(&five, 8).iter().map(Vec::len).collect::<Vec<_>>(), vec![5]);
+    }
+
+    /// Tests that exact keyword matches give the keyword and other colors their hex code.
+    #[test]
+    fn test_to_css_keyword() {
+        let keywords = css_extended_colors();
+
+        assert_eq!(Color::new(255, 99, 71).to_css_keyword(&keywords), "tomato");
+        assert_eq!(Color::new(255, 99, 72).to_css_keyword(&keywords), "tomato");
+        assert_eq!(Color::new(18, 52, 86).to_css_keyword(&keywords), "#123456");
+        assert_eq!(Color::new_rgba(255, 99, 71, 0).to_css_keyword(&keywords), "rgba(255, 99, 71, 0)");
+        assert_eq!(Color::new(255, 99, 71).to_css_keyword(&HashMap::new()), "#ff6347");
+    }
+
+    /// Tests writing colors to a buffer and reparsing them, and the error of a failing writer.
+    #[test]
+    fn test_write_colors_json() {
+        let colors = load_colors_from_file(SAMPLE_JSON).unwrap();
+        let mut buffer = Vec::new();
+        write_colors_json(&colors, &mut buffer).unwrap();
+
+        assert_eq!(load_colors_from_file(std::str::from_utf8(&buffer).unwrap()).unwrap(), colors);
+        assert_eq!(String::from_utf8(buffer).unwrap(), save_colors_to_string_sorted(&colors).unwrap());
+
+        struct BrokenPipe;
+        impl Write for BrokenPipe {
+            fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::new(std::io::ErrorKind::BrokenPipe, "closed"))
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        let err = write_colors_json(&colors, BrokenPipe).unwrap_err();
+        assert!(matches!(err, NamedColorsError::Io(err) if err.kind() == std::io::ErrorKind::BrokenPipe));
+    }
+
+    /// Tests that sorting by `sort_key` groups colors of similar hues together.
+    #[test]
+    fn test_sort_key() {
+        let mut colors = [
+            Color::new(0, 0, 255),
+            Color::new(255, 0, 0),
+            Color::new(0, 200, 0),
+            Color::new(0, 0, 128),
+            Color::new(200, 10, 0),
+            Color::new(30, 255, 30),
+        ];
+        colors.sort_by_key(Color::sort_key);
+
+        let names: Vec<&str> = colors.iter().map(Color::closest_basic_name).collect();
+        assert_eq!(names, ["red", "red", "green", "green", "blue", "blue"]);
+        assert_eq!(Color::new(255, 0, 0).sort_key(), 255 << 8 | 255);
+        assert!(Color::new(0, 0, 128).sort_key() < Color::new(0, 0, 255).sort_key());
+    }
+
+    /// Tests detecting grays with and without tolerance.
+    #[test]
+    fn test_is_grayscale() {
+        assert!(Color::new(128, 128, 128).is_grayscale(0));
+        assert!(!Color::new(128, 128, 140).is_grayscale(5));
+        assert!(Color::new(128, 128, 140).is_grayscale(12));
+        assert!(!Color::new(255, 0, 0).is_grayscale(100));
+    }
+
+    /// Tests OKLab/OKLCH round trips and that the lightness follows perceived brightness.
+    #[test]
+    fn test_oklab() {
+        for color in [Color::new(255, 0, 0), Color::new(70, 130, 180), Color::new(255, 228, 196), Color::new(12, 34, 56)] {
+            let (l, a, b) = color.to_oklab();
+            assert!(Color::from_oklab(l, a, b).approx_eq(&color, 1), "{:?}", color);
+            let (l, c, h) = color.to_oklch();
+            assert!(Color::from_oklch(l, c, h).approx_eq(&color, 1), "{:?}", color);
+        }
+
+        let (l, a, b) = Color::new(255, 255, 255).to_oklab();
+        assert!((l - 1.0).abs() < 1e-3 && a.abs() < 1e-3 && b.abs() < 1e-3);
+        let lightness: Vec<f32> = [0, 64, 128, 192, 255].iter().map(|&v| Color::new(v, v, v).to_oklab().0).collect();
+        assert!(lightness.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(Color::new(255, 255, 0).to_oklab().0 > Color::new(0, 0, 255).to_oklab().0);
+    }
+
+    /// Tests that the palette table is sorted and its columns are aligned.
+    #[test]
+    fn test_print_palette_table() {
+        let colors = HashMap::from([
+            ("red".to_string(), Color::new(255, 0, 0)),
+            ("aliceblue".to_string(), Color::new(240, 248, 255)),
+        ]);
+        let mut output = Vec::new();
+        print_palette_table(&colors, &mut output).unwrap();
+        let table = String::from_utf8(output).unwrap();
+
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with("aliceblue  #f0f8ff  rgb(240, 248, 255)"));
+        assert!(lines[1].ends_with("red        #ff0000  rgb(255, 0, 0)"));
+        assert_eq!(lines[1].contains("\x1b[48;2;255;0;0m"), cfg!(feature = "ansi"));
+    }
+
+    /// Tests resolving merge conflicts by keeping the brighter color.
+    #[test]
+    fn test_merge_colors_with() {
+        let mut base = HashMap::from([
+            ("a".to_string(), Color::new(10, 10, 10)),
+            ("b".to_string(), Color::new(250, 250, 250)),
+        ]);
+        let other = HashMap::from([
+            ("a".to_string(), Color::new(200, 200, 200)),
+            ("b".to_string(), Color::new(0, 0, 0)),
+            ("c".to_string(), Color::new(1, 2, 3)),
+        ]);
+        merge_colors_with(&mut base, &other, |_, a, b| if a.luminance() >= b.luminance() { *a } else { *b });
+
+        assert_eq!(base["a"], Color::new(200, 200, 200));
+        assert_eq!(base["b"], Color::new(250, 250, 250));
+        assert_eq!(base["c"], Color::new(1, 2, 3));
+    }
+
+    /// Tests comparing palettes with a per-channel tolerance.
+    #[test]
+    fn test_palettes_equivalent() {
+        let a = HashMap::from([
+            ("red".to_string(), Color::new(255, 0, 0)),
+            ("teal".to_string(), Color::new(0, 128, 128)),
+        ]);
+        let b = HashMap::from([
+            ("red".to_string(), Color::new(254, 1, 0)),
+            ("teal".to_string(), Color::new(1, 127, 129)),
+        ]);
+        assert!(palettes_equivalent(&a, &b, 1));
+        assert!(!palettes_equivalent(&a, &b, 0));
+
+        let mut renamed = b.clone();
+        let teal = renamed.remove("teal").unwrap();
+        renamed.insert("cyan".to_string(), teal);
+        assert!(!palettes_equivalent(&a, &renamed, 1));
+        assert!(!palettes_equivalent(&a, &HashMap::new(), 255));
+    }
+
+    /// Tests that subtractive mixing multiplies CMY like paint rather than averaging light.
+    #[test]
+    fn test_mix_subtractive() {
+        let blue = Color::new(30, 144, 255);
+        let yellow = Color::new(255, 255, 0);
+        let mixed = blue.mix_subtractive(&yellow);
+        assert_eq!(mixed, Color::new(30, 144, 0));
+        assert!(mixed.g > 100 && mixed.g > 3 * mixed.r && mixed.b == 0, "{:?}", mixed);
+
+        assert_eq!(Color::new(0, 0, 255).mix_subtractive(&yellow), Color::new(0, 0, 0));
+        assert_eq!(Color::new(128, 128, 128).mix_subtractive(&Color::new(128, 128, 128)), Color::new(64, 64, 64));
+        assert_eq!(Color::new(255, 255, 255).mix_subtractive(&Color::new(200, 100, 50)), Color::new(200, 100, 50));
+        assert_eq!(Color::new_rgba(255, 0, 0, 255).mix_subtractive(&Color::new_rgba(255, 255, 255, 101)).a, 178);
+    }
+
+    /// Tests that RYB mixing gives green even for pure blue and yellow.
+    #[test]
+    fn test_mix_ryb() {
+        let blue = Color::new(0, 0, 255);
+        let yellow = Color::new(255, 255, 0);
+        let mixed = blue.mix_ryb(&yellow);
+        assert!(mixed.g > 100 && mixed.r < 30 && mixed.b < 30, "{:?}", mixed);
+        assert!(blue.blend(&yellow, 0.5).is_grayscale(1));
+
+        let orange = Color::new(255, 0, 0).mix_ryb(&yellow);
+        assert!(orange.r > orange.g && orange.g > orange.b, "{:?}", orange);
+        assert_eq!(Color::new(128, 128, 128).mix_ryb(&Color::new(128, 128, 128)), Color::new(128, 128, 128));
+    }
+
+    /// Tests ranking colors from darkest to lightest.
+    #[test]
+    fn test_color_rank() {
+        let colors = HashMap::from([
+            ("white".to_string(), Color::new(255, 255, 255)),
+            ("black".to_string(), Color::new(0, 0, 0)),
+            ("gray".to_string(), Color::new(128, 128, 128)),
+            ("navy".to_string(), Color::new(0, 0, 128)),
+        ]);
+        assert_eq!(color_rank(&colors, "black"), Some(0));
+        assert_eq!(color_rank(&colors, "navy"), Some(1));
+        assert_eq!(color_rank(&colors, "gray"), Some(2));
+        assert_eq!(color_rank(&colors, "white"), Some(3));
+        assert_eq!(color_rank(&colors, "Black"), Some(0));
+        assert_eq!(color_rank(&colors, " WHITE "), Some(3));
+        assert_eq!(color_rank(&colors, "pink"), None);
+    }
+
+    /// Tests the header and block layout of the ASE export.
+    #[test]
+    fn test_to_ase() {
+        let colors = HashMap::from([
+            ("red".to_string(), Color::new(255, 0, 0)),
+            ("blue".to_string(), Color::new(0, 0, 255)),
+        ]);
+        let ase = to_ase(&colors);
+
+        assert_eq!(&ase[..4], b"ASEF");
+        assert_eq!(&ase[4..8], &[0, 1, 0, 0]);
+        assert_eq!(u32::from_be_bytes(ase[8..12].try_into().unwrap()), 2);
+
+        // "blue" comes first: block type, length, then 5 UTF-16 units for the name
+        assert_eq!(&ase[12..14], &[0, 1]);
+        let length = u32::from_be_bytes(ase[14..18].try_into().unwrap()) as usize;
+        assert_eq!(length, 2 + 10 + 4 + 12 + 2);
+        assert_eq!(&ase[18..20], &[0, 5]);
+        assert_eq!(&ase[20..30], &[0, b'b', 0, b'l', 0, b'u', 0, b'e', 0, 0]);
+        assert_eq!(&ase[30..34], b"RGB ");
+        assert_eq!(f32::from_be_bytes(ase[42..46].try_into().unwrap()), 1.0);
+
+        let swatches = ase[12..].windows(4).filter(|window| window == b"RGB ").count();
+        assert_eq!(swatches, 2);
+        // "red" is one UTF-16 unit shorter than "blue"
+        assert_eq!(ase.len(), 12 + (6 + length) + (6 + length - 2));
+    }
+
+    /// Tests that the sorted loader iterates alphabetically and folds the case of names.
+    #[test]
+    fn test_load_colors_sorted() {
+        let colors = load_colors_sorted().unwrap();
+        assert_eq!(colors.len(), builtin_colors().unwrap().len());
+        let names: Vec<&String> = colors.keys().collect();
+        assert!(names.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let colors = load_colors_sorted_from_str(r#"{"Teal": {"r": 0, "g": 128, "b": 128}, "navy": {"r": 0, "g": 0, "b": 128}}"#).unwrap();
+        assert_eq!(colors.keys().collect::<Vec<_>>(), ["navy", "teal"]);
+
+        let result = load_colors_sorted_from_str(r#"{"Red": {"r": 255, "g": 0, "b": 0}, "red": {"r": 254, "g": 0, "b": 0}}"#);
+        assert!(matches!(result, Err(NamedColorsError::DuplicateColor(name)) if name == "red"));
+    }
+
+    /// Tests that simulated color blindness confuses the expected pairs and keeps grays.
+    #[test]
+    fn test_simulate_colorblindness() {
+        let red = Color::new(255, 0, 0);
+        let green = Color::new(0, 128, 0);
+        let before = red.distance(&green, DistanceMetric::CIE76);
+        let after = red
+            .simulate_colorblindness(ColorBlindness::Deuteranopia)
+            .distance(&green.simulate_colorblindness(ColorBlindness::Deuteranopia), DistanceMetric::CIE76);
+        assert!(after < before / 2.0, "{} -> {}", before, after);
+
+        for kind in [ColorBlindness::Protanopia, ColorBlindness::Deuteranopia, ColorBlindness::Tritanopia] {
+            let gray = Color::new_rgba(128, 128, 128, 100).simulate_colorblindness(kind);
+            assert!(gray.approx_eq(&Color::new_rgba(128, 128, 128, 100), 2), "{:?}: {:?}", kind, gray);
+        }
+        let blue = Color::new(0, 0, 255);
+        assert!(blue.simulate_colorblindness(ColorBlindness::Deuteranopia).b > 200);
+    }
+
+    /// Tests that generated colors have evenly spaced hues.
+    #[test]
+    fn test_generate_distinct_colors() {
+        let colors = generate_distinct_colors(6);
+        assert_eq!(colors.len(), 6);
+        let mut hues = Vec::new();
+        for (i, color) in colors.iter().enumerate() {
+            let (h, s, l) = color.to_hsl();
+            assert!((h - (i as f32 * 137.5).rem_euclid(360.0)).abs() < 1.5, "{}: {}", i, h);
+            assert!((s - 0.65).abs() < 0.02 && (l - 0.5).abs() < 0.02);
+            hues.push(h);
+        }
+
+        // 6 distinct hues, at least 30° apart around the wheel (60° on average)
+        hues.sort_by(f32::total_cmp);
+        let gaps: Vec<f32> = hues.windows(2).map(|pair| pair[1] - pair[0]).chain([360.0 + hues[0] - hues[5]]).collect();
+        assert!(gaps.iter().all(|gap| *gap >= 30.0), "{:?}", gaps);
+
+        assert_eq!(generate_distinct_colors(5)[..], colors[..5]);
+        assert!(generate_distinct_colors(0).is_empty());
+    }
+
+    /// Tests looking up a color with a fallback.
+    #[test]
+    fn test_get_color_or() {
+        let colors = HashMap::from([("red".to_string(), Color::new(255, 0, 0))]);
+        assert_eq!(get_color_or(&colors, "Red", (1, 2, 3)), (255, 0, 0));
+        assert_eq!(get_color_or(&colors, "blue", (1, 2, 3)), (1, 2, 3));
+    }
+
+    /// Tests resolving names, hex codes and functions against the builtin colors.
+    #[test]
+    fn test_resolve() {
+        assert_eq!(resolve("red").unwrap(), Color::new(255, 0, 0));
+        assert_eq!(resolve(" #00ff00 ").unwrap(), Color::new(0, 255, 0));
+        assert_eq!(resolve("rgb(0,0,255)").unwrap(), Color::new(0, 0, 255));
+        assert!(matches!(resolve("notacolor"), Err(NamedColorsError::ColorNotFound(_))));
+        assert!(matches!(resolve("#12"), Err(NamedColorsError::InvalidHex(_))));
+    }
+
+    /// Tests the per-channel bounds of a palette.
+    #[test]
+    fn test_palette_bounds() {
+        let colors = HashMap::from([
+            ("a".to_string(), Color::new(10, 200, 30)),
+            ("b".to_string(), Color::new(50, 100, 90)),
+            ("c".to_string(), Color::new(30, 150, 0)),
+        ]);
+        assert_eq!(palette_bounds(&colors), Some((Color::new(10, 100, 0), Color::new(50, 200, 90))));
+        assert_eq!(palette_bounds(&HashMap::new()), None);
+    }
+
+    /// Tests that consuming the map yields every entry sorted by name.
+    #[test]
+    fn test_into_sorted_vec() {
+        let colors = builtin_colors().unwrap();
+        let count = colors.len();
+        let entries = into_sorted_vec(colors);
+        assert_eq!(entries.len(), count);
+        assert!(entries.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert!(entries.contains(&("red".to_string(), Color::new(255, 0, 0))));
+    }
+
+    /// Tests that excluded names are skipped, whatever their case.
+    #[test]
+    fn test_nearest_color_excluding() {
+        let colors = HashMap::from([
+            ("red".to_string(), Color::new(255, 0, 0)),
+            ("darkred".to_string(), Color::new(139, 0, 0)),
+            ("blue".to_string(), Color::new(0, 0, 255)),
+        ]);
+        let nearest = |exclude: &[&str]| nearest_color_excluding(&colors, 250, 0, 0, exclude, DistanceMetric::Euclidean);
+        assert_eq!(nearest(&[]), Some(("red".to_string(), (255, 0, 0))));
+        assert_eq!(nearest(&["RED"]), Some(("darkred".to_string(), (139, 0, 0))));
+        assert_eq!(nearest(&["red", "darkred", "blue"]), None);
+    }
+
+    /// Tests hue angles, including across the 0° boundary and for grays.
+    #[test]
+    fn test_hue_angle_between() {
+        let red = Color::new(255, 0, 0);
+        let angle = |a: &Color, b: &Color| hue_angle_between(a, b).unwrap();
+        assert!((angle(&red, &Color::new(0, 255, 255)) - 180.0).abs() < 0.5);
+        assert!(angle(&red, &Color::new(255, 165, 0)) < 45.0);
+        assert!((angle(&Color::new(255, 0, 64), &Color::new(255, 64, 0)) - 30.0).abs() < 1.0);
+        assert_eq!(hue_angle_between(&red, &Color::new(128, 128, 128)), None);
+    }
+
+    /// Tests that quantized channels only take the expected levels.
+    #[test]
+    fn test_quantize() {
+        for value in 0..=255u8 {
+            let color = Color::new(value, 255 - value, value / 2).quantize(2);
+            assert!([color.r, color.g, color.b].iter().all(|&c| c == 0 || c == 255), "{:?}", color);
+            let color = Color::new(value, value, value).quantize(4);
+            assert!([0, 85, 170, 255].contains(&color.r));
+        }
+        assert_eq!(Color::new(127, 128, 0).quantize(0), Color::new(0, 255, 0));
+        assert_eq!(Color::new(12, 34, 56).quantize(255), Color::new(12, 34, 56));
+        assert_eq!(Color::new_rgba(10, 10, 10, 7).quantize(2).a, 7);
+    }
+
+    /// Tests extracting colors of several formats from a stylesheet.
+    #[test]
+    fn test_extract_colors_from_css() {
+        let css = r#"
+            /* brand: orange */
+            .red, #fade:hover {
+                color: #ff0000;
+                background: rgba(0, 0, 255, 0.5) url(data:image/png;base64,AAAA);
+                border: 1px solid Navy;
+                outline-color: hsl(120, 100%, 50%);
+            }
+            @media (max-width: 600px) {
+                a { background-image: linear-gradient(white, #00f8); font-weight: 100 }
+            }
+        "#;
+        assert_eq!(
+            extract_colors_from_css(css),
+            vec![
+                Color::new(255, 0, 0),
+                Color::new_rgba(0, 0, 255, 128),
+                Color::new(0, 0, 128),
+                Color::new(0, 255, 0),
+                Color::new(255, 255, 255),
+                Color::new_rgba(0, 0, 255, 136),
+            ]
+        );
+        assert!(extract_colors_from_css("p { margin: 0 auto; }").is_empty());
+    }
+
+    /// Tests the steps of the Material tonal palette.
+    #[test]
+    fn test_material_scale() {
+        let seed = Color::new(33, 150, 243);
+        let scale = seed.material_scale();
+        assert_eq!(scale.len(), 14);
+        assert_eq!(scale["500"], seed);
+        assert!(scale["50"].to_hsl().2 > 0.9);
+        assert!(scale["900"].to_hsl().2 < 0.3);
+
+        let steps = ["50", "100", "200", "300", "400", "500", "600", "700", "800", "900"];
+        assert!(steps.windows(2).all(|pair| scale[pair[0]].luminance() > scale[pair[1]].luminance()));
+        assert!(scale["A200"].to_hsl().1 >= scale["200"].to_hsl().1);
+    }
+
+    /// Tests that a rainbow palette is more diverse than a grayscale one, and that sampling stays close.
+    #[test]
+    fn test_palette_diversity() {
+        let grays: HashMap<String, Color> = (0..6).map(|i| (format!("gray{}", i), Color::new(100 + i * 10, 100 + i * 10, 100 + i * 10))).collect();
+        let rainbow: HashMap<String, Color> = generate_distinct_colors(6).into_iter().enumerate().map(|(i, color)| (format!("hue{}", i), color)).collect();
+        for metric in [DistanceMetric::Euclidean, DistanceMetric::CIE76] {
+            assert!(palette_diversity(&grays, metric) < palette_diversity(&rainbow, metric));
+        }
+        assert_eq!(palette_diversity(&HashMap::new(), DistanceMetric::Euclidean), 0.0);
+
+        let colors = builtin_colors().unwrap();
+        let full = palette_diversity(&colors, DistanceMetric::Euclidean);
+        let sampled = palette_diversity_sampled(&colors, DistanceMetric::Euclidean, 50);
+        assert!((full - sampled).abs() / full < 0.15, "{} vs {}", full, sampled);
+    }
+
+    /// Tests reading colors from prefixed environment-style pairs.
+    #[test]
+    fn test_load_colors_from_env_pairs() {
+        let pairs: Vec<(String, String)> = [
+            ("THEME_RED", "#ff0000"),
+            ("THEME_DARK_BLUE", "rgb(0, 0, 139)"),
+            ("PATH", "/usr/bin"),
+        ]
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+        let colors = load_colors_from_env_pairs(&pairs, "THEME_").unwrap();
+        assert_eq!(colors.len(), 2);
+        assert_eq!(colors["red"], Color::new(255, 0, 0));
+        assert_eq!(colors["dark_blue"], Color::new(0, 0, 139));
+
+        let bad = vec![("THEME_ACCENT".to_string(), "tomato".to_string())];
+        let err = load_colors_from_env_pairs(&bad, "THEME_").unwrap_err();
+        assert!(err.to_string().contains("THEME_ACCENT"));
+        let empty = vec![("THEME_".to_string(), "#000".to_string())];
+        assert!(matches!(load_colors_from_env_pairs(&empty, "THEME_"), Err(NamedColorsError::InvalidName(_))));
+    }
+
+    /// Tests that muted colors are less saturated with the same hue.
+    #[test]
+    fn test_muted() {
+        for color in [Color::new(255, 0, 0), Color::new(30, 144, 255), Color::new(255, 250, 205)] {
+            let (h, s, l) = color.to_hsl();
+            let (muted_h, muted_s, muted_l) = color.muted().to_hsl();
+            assert!(muted_s < s, "{:?}", color);
+            assert!((muted_h - h).abs() < 2.0, "{:?}", color);
+            assert!((muted_l - 0.5).abs() <= (l - 0.5).abs() + 0.01);
+        }
+        assert_eq!(Color::new(128, 128, 128).muted(), Color::new(128, 128, 128));
+    }
+
+    /// Tests that the basic color enum matches the builtin colors and parses names.
+    #[test]
+    fn test_basic_color() {
+        let colors = builtin_colors().unwrap();
+        assert_eq!(BasicColor::Red.as_color(), colors["red"]);
+        for color in BasicColor::ALL {
+            assert_eq!(BasicColor::try_from(color.name()).unwrap(), color);
+            if let Some(builtin) = colors.get(color.name()).filter(|_| color != BasicColor::Green) {
+                assert_eq!(color.as_color(), *builtin, "{}", color.name());
+            }
+        }
+        assert_eq!(BasicColor::try_from(" NAVY ").unwrap(), BasicColor::Navy);
+        assert!(matches!(BasicColor::try_from("coral"), Err(NamedColorsError::ColorNotFound(_))));
+    }
+
+    /// Tests that theme pairs are readable and only tinted with contrast to spare.
+    #[test]
+    fn test_theme_pair() {
+        let blue = Color::new(30, 110, 220);
+        let (background, foreground) = theme_pair(&blue);
+        assert_eq!(background, blue);
+        assert_eq!(foreground, Color::new(255, 255, 255));
+        assert!(background.contrast_ratio(&foreground) >= 4.5);
+
+        let (background, foreground) = theme_pair(&Color::new(10, 20, 60));
+        assert_ne!(foreground, Color::new(255, 255, 255));
+        assert!(foreground.r > 200 && background.contrast_ratio(&foreground) >= 7.0);
+
+        for value in (0..=255u8).step_by(5) {
+            let (background, foreground) = theme_pair(&Color::new(value, 255 - value, value / 2));
+            assert!(background.contrast_ratio(&foreground) >= 4.5, "{:?}", background);
+        }
+    }
+
+    /// Tests the palette state through edits, undos and redos.
+    #[test]
+    fn test_editable_palette() {
+        let red = Color::new(255, 0, 0);
+        let dark_red = Color::new(139, 0, 0);
+        let mut palette = EditablePalette::from(HashMap::from([("blue".to_string(), Color::new(0, 0, 255))]));
+
+        palette.add("Red", red).unwrap();
+        assert_eq!(palette.colors().get("red"), Some(&red));
+        assert!(palette.undo());
+        assert_eq!(palette.colors().get("red"), None);
+        assert!(palette.redo());
+        assert_eq!(palette.colors().get("red"), Some(&red));
+        assert!(!palette.redo());
+
+        assert_eq!(palette.update("red", dark_red).unwrap(), red);
+        assert_eq!(palette.remove("blue").unwrap(), Color::new(0, 0, 255));
+        assert_eq!(palette.colors().len(), 1);
+        assert!(palette.undo());
+        assert!(palette.undo());
+        assert_eq!(palette.colors().get("red"), Some(&red));
+        assert_eq!(palette.colors().len(), 2);
+
+        // A new edit drops the undone ones
+        palette.add("green", Color::new(0, 128, 0)).unwrap();
+        assert!(!palette.redo());
+        assert!(matches!(palette.add("RED", red), Err(NamedColorsError::DuplicateColor(_))));
+        assert!(matches!(palette.remove("pink"), Err(NamedColorsError::ColorNotFound(_))));
+        assert!(matches!(palette.add(" - ", red), Err(NamedColorsError::InvalidName(name)) if name == " - "));
+
+        while palette.undo() {}
+        assert_eq!(palette.into_inner(), HashMap::from([("blue".to_string(), Color::new(0, 0, 255))]));
+    }
+
+    /// Tests the Flutter color constructor output.
+    #[test]
+    fn test_to_flutter() {
+        assert_eq!(Color::new(255, 0, 0).to_flutter(), "Color(0xFFFF0000)");
+        assert_eq!(Color::new_rgba(0, 128, 255, 0x80).to_flutter(), "Color(0x800080FF)");
+    }
+
+    /// Tests finding names from slightly-off hex codes.
+    #[test]
+    fn test_get_name_by_hex_approx() {
+        let colors = builtin_colors().unwrap();
+        assert_eq!(get_name_by_hex_approx(&colors, "#fe0100", 2), Some("red".to_string()));
+        assert_eq!(get_name_by_hex_approx(&colors, "#fe0100", 0), None);
+        assert_eq!(get_name_by_hex_approx(&colors, "#ff0000", 0), Some("red".to_string()));
+        assert_eq!(get_name_by_hex_approx(&colors, "nothex", 10), None);
+    }
+
+    /// Tests loading JSON Lines and reporting the malformed line.
+    #[test]
+    fn test_load_colors_from_jsonl() {
+        let data = "{\"name\": \"red\", \"r\": 255, \"g\": 0, \"b\": 0}\n\n{\"name\": \"navy\", \"r\": 0, \"g\": 0, \"b\": 128}\n";
+        let colors = load_colors_from_jsonl(data.as_bytes()).unwrap();
+        assert_eq!(colors.len(), 2);
+        assert_eq!(colors["navy"], Color::new(0, 0, 128));
+
+        let data = "{\"name\": \"red\", \"r\": 255, \"g\": 0, \"b\": 0}\n{\"name\": \"bad\", \"r\": 300, \"g\": 0, \"b\": 0}\n";
+        match load_colors_from_jsonl(data.as_bytes()) {
+            Err(NamedColorsError::InvalidLine { line, .. }) => assert_eq!(line, 2),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    /// Tests that warm whites have a lower color temperature than cool ones.
+    #[test]
+    fn test_to_kelvin_approx() {
+        let white = Color::new(255, 255, 255).to_kelvin_approx();
+        assert!((white - 6500.0).abs() < 100.0, "{}", white);
+        let warm = Color::new(255, 214, 170).to_kelvin_approx();
+        let cool = Color::new(201, 218, 255).to_kelvin_approx();
+        assert!(warm < 4500.0 && cool > 8000.0, "{} {}", warm, cool);
+        assert!((Color::new(0, 0, 0).to_kelvin_approx() - white).abs() < 100.0);
+    }
+
+    /// Tests that OKLab gradients keep their endpoints and avoid the gray sRGB midpoint.
+    #[test]
+    fn test_gradient_oklab() {
+        let blue = Color::new(0, 0, 255);
+        let yellow = Color::new(255, 255, 0);
+        let gradient = gradient_oklab(&blue, &yellow, 5);
+        assert_eq!(gradient.len(), 5);
+        assert!(gradient[0].approx_eq(&blue, 1) && gradient[4].approx_eq(&yellow, 1));
+
+        let srgb_midpoint = blue.blend(&yellow, 0.5);
+        assert!(srgb_midpoint.is_grayscale(1));
+        assert!(!gradient[2].approx_eq(&srgb_midpoint, 10), "{:?}", gradient[2]);
+
+        let lightness: Vec<f32> = gradient.iter().map(|color| color.to_oklab().0).collect();
+        assert!(lightness.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(gradient_oklab(&blue, &yellow, 1), vec![blue]);
+        assert!(gradient_oklab(&blue, &yellow, 0).is_empty());
+    }
+
+    /// Tests that higher-priority layers shadow lower ones, whatever the order they're added in.
+    #[test]
+    fn test_resolver_config() {
+        let config = ResolverConfig::new()
+            .with_layer("user", 10, HashMap::from([("red".to_string(), Color::new(200, 0, 0))]))
+            .with_layer("builtin", 0, builtin_colors().unwrap())
+            .with_layer("workspace", 5, HashMap::from([("red".to_string(), Color::new(100, 0, 0)), ("brand".to_string(), Color::new(1, 2, 3))]));
+
+        assert_eq!(resolve_name(&config, "Red"), Some((200, 0, 0)));
+        assert_eq!(config.layer_of("red"), Some("user"));
+        assert_eq!(resolve_name(&config, "brand"), Some((1, 2, 3)));
+        assert_eq!(config.layer_of("brand"), Some("workspace"));
+        assert_eq!(resolve_name(&config, "navy"), Some((0, 0, 128)));
+        assert_eq!(resolve_name(&config, "nope"), None);
+        assert_eq!(config.layer_of("nope"), None);
+    }
+
+    /// Tests that on-colors reach the requested contrast with a tint of the base hue.
+    #[test]
+    fn test_on_color() {
+        for background in [Color::new(220, 20, 60), Color::new(0, 100, 200), Color::new(255, 215, 0), Color::new(40, 40, 40)] {
+            // 4.5:1 is always reachable with black or white
+            for ratio in [3.0, 4.5] {
+                let on = background.on_color(ratio);
+                assert!(on.contrast_ratio(&background) >= ratio, "{:?} {} -> {:?}", background, ratio, on);
+            }
+        }
+
+        let crimson = Color::new(220, 20, 60);
+        let on = crimson.on_color(3.0);
+        assert!(on != Color::new(255, 255, 255) && on != Color::new(0, 0, 0), "{:?}", on);
+        assert!((on.to_hsl().0 - crimson.to_hsl().0).abs() < 3.0);
+        assert!(Color::new(40, 40, 40).on_color(7.0).contrast_ratio(&Color::new(40, 40, 40)) >= 7.0);
+        assert_eq!(crimson.on_color(7.0), crimson.best_text_color());
+    }
+
+    /// Tests converting a whole palette to HSL and CMYK.
+    #[test]
+    fn test_convert_palette() {
+        let colors = HashMap::from([
+            ("red".to_string(), Color::new(255, 0, 0)),
+            ("navy".to_string(), Color::new(0, 0, 128)),
+        ]);
+        let hsl = convert_palette(&colors, ColorSpace::Hsl);
+        assert_eq!(hsl.len(), 2);
+        assert_eq!(hsl["red"], ConvertedColor::Three(0.0, 1.0, 0.5));
+        let ConvertedColor::Three(h, _, l) = hsl["navy"] else { panic!("{:?}", hsl["navy"]) };
+        assert!((h - 240.0).abs() < 0.01 && (l - 0.251).abs() < 0.001);
+
+        let cmyk = convert_palette(&colors, ColorSpace::Cmyk);
+        assert_eq!(cmyk["red"], ConvertedColor::Four(0.0, 1.0, 1.0, 0.0));
+        assert_eq!(Color::new(0, 0, 0).to_cmyk(), (0.0, 0.0, 0.0, 1.0));
+        assert!(matches!(convert_palette(&colors, ColorSpace::Oklab)["red"], ConvertedColor::Three(..)));
+    }
+
+    /// Tests neon detection on vivid, dusty, pale and dark colors.
+    #[test]
+    fn test_is_neon() {
+        assert!(Color::new(255, 0, 255).is_neon(0.9, 0.8));
+        assert!(Color::new(57, 255, 20).is_neon(0.9, 0.8));
+        assert!(!Color::new(220, 160, 170).is_neon(0.9, 0.8));
+        assert!(!Color::new(255, 240, 250).is_neon(0.9, 0.8));
+        assert!(!Color::new(128, 0, 0).is_neon(0.9, 0.8));
+        assert!(Color::new(128, 0, 0).is_neon(0.9, 0.5));
+    }
+
+    /// Tests the generated Rust constants and identifier sanitizing.
+    #[test]
+    fn test_to_rust_source() {
+        let colors = HashMap::from([
+            ("sky blue".to_string(), Color::new(135, 206, 235)),
+            ("sky-blue".to_string(), Color::new(0, 0, 1)),
+            ("3d-red".to_string(), Color::new_rgba(255, 0, 0, 128)),
+        ]);
+        let source = to_rust_source(&colors, "My Palette");
+        let lines: Vec<&str> = source.lines().collect();
+
+        assert_eq!(lines[0], "pub mod my_palette {");
+        assert_eq!(lines[1], "    use named_colors::colors::Color;");
+        assert_eq!(lines[3], "    pub const _3D_RED: Color = Color { r: 255, g: 0, b: 0, a: 128 };");
+        assert_eq!(lines[4], "    pub const SKY_BLUE: Color = Color { r: 135, g: 206, b: 235, a: 255 };");
+        assert_eq!(lines[5], "    pub const SKY_BLUE_2: Color = Color { r: 0, g: 0, b: 1, a: 255 };");
+        assert_eq!(lines[6], "}");
+        assert!(to_rust_source(&HashMap::new(), "type").starts_with("pub mod r#type {"));
+        for reserved in ["yield", "try", "macro", "gen", "Virtual"] {
+            let expected = format!("pub mod r#{} {{", reserved.to_ascii_lowercase());
+            assert!(to_rust_source(&HashMap::new(), reserved).starts_with(&expected), "{}", reserved);
+        }
+        assert!(to_rust_source(&HashMap::new(), "self").starts_with("pub mod self_ {"));
+    }
+
+    /// Tests finding the colors that complete a harmony.
+    #[test]
+    fn test_harmonious_with() {
+        let colors = HashMap::from([
+            ("cyan".to_string(), Color::new(0, 255, 255)),
+            ("teal".to_string(), Color::new(0, 128, 128)),
+            ("red".to_string(), Color::new(255, 0, 0)),
+            ("gray".to_string(), Color::new(128, 128, 128)),
+            ("lime".to_string(), Color::new(0, 255, 0)),
+            ("blue".to_string(), Color::new(0, 0, 255)),
+        ]);
+        let red = Color::new(255, 0, 0);
+        assert_eq!(harmonious_with(&colors, &red, HarmonyScheme::Complementary, 5.0), vec!["cyan", "teal"]);
+        assert_eq!(harmonious_with(&colors, &red, HarmonyScheme::Triadic, 5.0), vec!["blue", "lime"]);
+        // Target hues past 360° wrap around to red
+        assert_eq!(harmonious_with(&colors, &Color::new(0, 255, 10), HarmonyScheme::Triadic, 5.0), vec!["blue", "red"]);
+        assert!(harmonious_with(&colors, &Color::new(50, 50, 50), HarmonyScheme::Complementary, 180.0).is_empty());
+    }
+}
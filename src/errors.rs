@@ -0,0 +1,115 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// Errors returned by the color loading and manipulation functions.
+#[derive(Debug)]
+pub enum NamedColorsError {
+    /// Reading or writing a palette failed.
+    Io(io::Error),
+    /// The palette JSON could not be parsed.
+    ParseError(serde_json::Error),
+    /// A color with the same (normalized) name already exists.
+    DuplicateColor(String),
+    /// The color name is empty once normalized.
+    InvalidName(String),
+    /// The palette JSON declares the same keys more than once.
+    DuplicateKeys(Vec<String>),
+    /// No color with this name exists in the map.
+    ColorNotFound(String),
+    /// The operation needs at least one color.
+    NoColors,
+    /// The string is not a valid hex color code.
+    InvalidHex(String),
+    /// A line of a line-based palette could not be parsed (line numbers start at 1).
+    InvalidLine { line: usize, message: String },
+    /// No color with this name exists, with the closest known names (possibly none).
+    UnknownColor { name: String, suggestions: Vec<String> },
+    /// A color string (e.g. `hsl(...)`) is malformed; the message describes the problem.
+    InvalidFormat(String),
+    /// Mixing weights must be non-negative and add up to more than zero.
+    InvalidWeights,
+    /// The entry for this color name could not be parsed.
+    InvalidColor { name: String, source: serde_json::Error },
+    /// The palette file at this path could not be parsed.
+    InvalidFile { path: PathBuf, source: serde_json::Error },
+    /// A `{"ref": ..}` entry points to a color that doesn't exist.
+    UnresolvedRef { name: String, target: String },
+    /// `{"ref": ..}` entries point to each other in a loop; the names form the cycle, first name repeated last.
+    CyclicRef(Vec<String>),
+}
+
+impl NamedColorsError {
+    /// Returns the `(line, column)` of a JSON syntax or data error, both starting at 1.
+    ///
+    /// # Returns:
+    /// * `None` for errors that aren't tied to a position in the input.
+    pub fn location(&self) -> Option<(usize, usize)> {
+        let err = match self {
+            NamedColorsError::ParseError(err) => err,
+            NamedColorsError::InvalidColor { source, .. } | NamedColorsError::InvalidFile { source, .. } => source,
+            _ => return None,
+        };
+        // serde_json reports line 0 for errors raised outside of the parser
+        if err.line() == 0 {
+            None
+        } else {
+            Some((err.line(), err.column()))
+        }
+    }
+}
+
+impl fmt::Display for NamedColorsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NamedColorsError::Io(err) => write!(f, "I/O error: {}", err),
+            NamedColorsError::ParseError(err) => write!(f, "failed to parse colors: {}", err),
+            NamedColorsError::DuplicateColor(name) => write!(f, "color '{}' already exists", name),
+            NamedColorsError::InvalidName(name) => write!(f, "invalid color name '{}'", name),
+            NamedColorsError::DuplicateKeys(names) => write!(f, "duplicate color keys: {}", names.join(", ")),
+            NamedColorsError::ColorNotFound(name) => write!(f, "color '{}' not found", name),
+            NamedColorsError::NoColors => write!(f, "no colors given"),
+            NamedColorsError::InvalidHex(hex) => write!(f, "invalid hex color '{}'", hex),
+            NamedColorsError::InvalidLine { line, message } => write!(f, "line {}: {}", line, message),
+            NamedColorsError::InvalidFormat(message) => write!(f, "invalid color format: {}", message),
+            NamedColorsError::InvalidColor { name, source } => write!(f, "failed to parse color '{}': {}", name, source),
+            NamedColorsError::InvalidFile { path, source } => write!(f, "failed to parse '{}': {}", path.display(), source),
+            NamedColorsError::UnresolvedRef { name, target } => write!(f, "color '{}' refers to unknown color '{}'", name, target),
+            NamedColorsError::CyclicRef(names) => write!(f, "cyclic color references: {}", names.join(" -> ")),
+            NamedColorsError::InvalidWeights => write!(f, "weights must be non-negative and add up to more than zero"),
+            NamedColorsError::UnknownColor { name, suggestions } => {
+                write!(f, "color '{}' not found", name)?;
+                if !suggestions.is_empty() {
+                    let quoted: Vec<String> = suggestions.iter().map(|s| format!("'{}'", s)).collect();
+                    write!(f, ", did you mean {}?", quoted.join(" or "))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for NamedColorsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            NamedColorsError::Io(err) => Some(err),
+            NamedColorsError::ParseError(err) => Some(err),
+            NamedColorsError::InvalidColor { source, .. } => Some(source),
+            NamedColorsError::InvalidFile { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for NamedColorsError {
+    fn from(err: io::Error) -> Self {
+        NamedColorsError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for NamedColorsError {
+    fn from(err: serde_json::Error) -> Self {
+        NamedColorsError::ParseError(err)
+    }
+}
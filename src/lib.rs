@@ -1,4 +1,12 @@
 pub mod colors;
+mod css;
+pub mod errors;
+mod macros;
+pub mod prelude;
+#[cfg(feature = "compressed-assets")]
+mod inflate;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 use colors::load_colors;
 
@@ -15,15 +23,18 @@ use colors::load_colors;
 ///     }
 /// }
 /// ```
-
 pub async fn get_color_by_name(color_name: &str) -> Option<(u8, u8, u8)> {
     // Load the colors from the cache/download function
     let color_data = load_colors().await.unwrap_or_default();
     
     // Convert the color name to lowercase to standardize the search
-    let color_name = color_name.to_lowercase();
+    let color_name = colors::fold_case(color_name);
     
-    if let Some(color) = color_data.get(&color_name) {
+    // Fall back to the aliases registered with `colors::add_alias`
+    let color = color_data
+        .get(&color_name)
+        .or_else(|| colors::alias_target(&color_name).and_then(|target| color_data.get(&target)));
+    if let Some(color) = color {
         // Extract RGB values from the JSON
         let r = color["r"].as_u64().unwrap_or(0) as u8;
         let g = color["g"].as_u64().unwrap_or(0) as u8;
@@ -64,6 +75,13 @@ mod tests {
         assert_eq!(color_lowercase, Some((255, 0, 0)));  // Check with lowercase name
     }
 
+    /// Tests that aliases registered with `add_alias` are resolved.
+    #[tokio::test]
+    async fn test_get_color_by_name_alias() {
+        colors::add_alias(&colors::builtin_colors().unwrap(), "grey", "gray").unwrap();
+        assert_eq!(get_color_by_name("Grey").await, Some((128, 128, 128)));
+    }
+
     /// Tests if the `load_colors` function works correctly.
     /// In this test, we simply check that loading the colors is successful.
     #[tokio::test]
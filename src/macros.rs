@@ -0,0 +1,36 @@
+/// Builds a [`Color`](crate::colors::Color) at compile time from a hex code or RGB channels.
+///
+/// Hex codes are parsed in a `const` context, so an invalid code is a compile error. Codes that
+/// aren't valid Rust tokens once the `#` is removed (e.g. `#1eff00`) can be given as a string.
+///
+/// ```rust
+/// use named_colors::color;
+/// use named_colors::colors::Color;
+///
+/// const BRAND: Color = color!(#0052cc);
+/// assert_eq!(BRAND, Color::new(0, 82, 204));
+/// assert_eq!(color!("#1eff00"), Color::new(30, 255, 0));
+/// assert_eq!(color!(255, 0, 0), Color::new(255, 0, 0));
+/// ```
+///
+/// ```compile_fail
+/// use named_colors::color;
+///
+/// let invalid = color!(#ff00zz);
+/// ```
+#[macro_export]
+macro_rules! color {
+    (# $hex:tt) => {
+        $crate::color!(stringify!($hex))
+    };
+    ($hex:expr) => {{
+        const COLOR: $crate::colors::Color = match $crate::colors::parse_hex_const($hex) {
+            Some(color) => color,
+            None => panic!("invalid hex color"),
+        };
+        COLOR
+    }};
+    ($r:expr, $g:expr, $b:expr) => {
+        $crate::colors::Color::new($r, $g, $b)
+    };
+}
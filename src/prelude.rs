@@ -0,0 +1,16 @@
+//! Re-exports of the most commonly used items.
+//!
+//! ```rust
+//! use named_colors::prelude::*;
+//! use std::collections::HashMap;
+//!
+//! let mut colors: HashMap<String, Color> = HashMap::new();
+//! add_color(&mut colors, "brand blue", 0, 82, 204).unwrap();
+//!
+//! let err: NamedColorsError = add_color(&mut colors, "Brand-Blue", 0, 0, 0).unwrap_err();
+//! println!("{}", err);
+//! ```
+
+pub use crate::colors::{add_color, load_colors, Color};
+pub use crate::errors::NamedColorsError;
+pub use crate::get_color_by_name;
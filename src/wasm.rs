@@ -0,0 +1,117 @@
+//! A `Color` class for JavaScript, so web frontends can build and read colors without JSON marshaling.
+//!
+//! Build the crate for `wasm32-unknown-unknown` with the `wasm` feature and without the default
+//! `download` feature (e.g. with `wasm-pack build -- --no-default-features --features wasm`):
+//!
+//! ```js
+//! import { Color } from "named_colors";
+//!
+//! const tomato = Color.fromHex("#ff6347");
+//! console.log(tomato.r, tomato.g, tomato.b, tomato.toHex());
+//! const red = Color.named("Red");  // undefined if there is no such builtin color
+//! ```
+
+use crate::colors::{get_color, shared_builtin, Color};
+use wasm_bindgen::prelude::*;
+
+/// A [`Color`] exported to JavaScript as the `Color` class, with getters for its channels.
+#[wasm_bindgen(js_name = Color)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WasmColor(Color);
+
+#[wasm_bindgen(js_class = Color)]
+impl WasmColor {
+    /// Creates a color from its channels; it is opaque when `a` is omitted.
+    #[wasm_bindgen(constructor)]
+    pub fn new(r: u8, g: u8, b: u8, a: Option<u8>) -> WasmColor {
+        WasmColor(Color::new_rgba(r, g, b, a.unwrap_or(255)))
+    }
+
+    /// Parses a hex color code (see [`Color::from_hex`]).
+    ///
+    /// # Returns:
+    /// * An `Error` thrown in JavaScript if the string isn't a valid hex code.
+    #[wasm_bindgen(js_name = fromHex)]
+    pub fn from_hex(hex: &str) -> Result<WasmColor, JsError> {
+        Ok(WasmColor(Color::from_hex(hex)?))
+    }
+
+    /// Looks up a builtin color by name, ignoring case (see [`get_color`]).
+    ///
+    /// # Returns:
+    /// * `undefined` in JavaScript if there is no builtin color with this name.
+    pub fn named(name: &str) -> Option<WasmColor> {
+        get_color(&shared_builtin(), name).map(|color| WasmColor(*color))
+    }
+
+    /// Formats the color as a lowercase `#rrggbb` hex code (see [`Color::to_hex`]).
+    #[wasm_bindgen(js_name = toHex)]
+    pub fn to_hex(&self) -> String {
+        self.0.to_hex()
+    }
+
+    /// The red channel.
+    #[wasm_bindgen(getter)]
+    pub fn r(&self) -> u8 {
+        self.0.r
+    }
+
+    /// The green channel.
+    #[wasm_bindgen(getter)]
+    pub fn g(&self) -> u8 {
+        self.0.g
+    }
+
+    /// The blue channel.
+    #[wasm_bindgen(getter)]
+    pub fn b(&self) -> u8 {
+        self.0.b
+    }
+
+    /// The alpha channel, `255` being fully opaque.
+    #[wasm_bindgen(getter)]
+    pub fn a(&self) -> u8 {
+        self.0.a
+    }
+}
+
+impl From<Color> for WasmColor {
+    fn from(color: Color) -> Self {
+        WasmColor(color)
+    }
+}
+
+impl From<WasmColor> for Color {
+    fn from(color: WasmColor) -> Self {
+        color.0
+    }
+}
+
+/// Unit tests for the JavaScript `Color` class.
+///
+/// They run in a JavaScript engine with `wasm-pack test --node -- --no-default-features --features wasm`.
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    /// Tests a hex code round trip through the class.
+    #[wasm_bindgen_test]
+    fn test_hex_round_trip() {
+        let tomato = WasmColor::from_hex("#FF6347").unwrap();
+        assert_eq!((tomato.r(), tomato.g(), tomato.b(), tomato.a()), (255, 99, 71, 255));
+        assert_eq!(tomato.to_hex(), "#ff6347");
+        assert_eq!(WasmColor::from_hex(&tomato.to_hex()).unwrap(), tomato);
+
+        assert!(WasmColor::from_hex("nothex").is_err());
+    }
+
+    /// Tests building colors and looking up builtin ones.
+    #[wasm_bindgen_test]
+    fn test_new_and_named() {
+        assert_eq!(WasmColor::new(0, 82, 204, None).a(), 255);
+        assert_eq!(Color::from(WasmColor::new(0, 82, 204, Some(100))), Color::new_rgba(0, 82, 204, 100));
+        assert_eq!(WasmColor::named("Red"), Some(WasmColor::new(255, 0, 0, None)));
+        assert_eq!(WasmColor::named("not a color"), None);
+    }
+}